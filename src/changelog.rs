@@ -0,0 +1,300 @@
+use crate::{
+    config::{config, ChangelogConfig},
+    edition::Edition,
+    history::ReleaseSummary,
+    IssueData, Ticket,
+};
+use chrono::NaiveDate;
+use color_eyre::{eyre::eyre, Result};
+use regex::Regex;
+use std::{fs, io::ErrorKind};
+use tracing::info;
+
+const HEADER: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).
+";
+
+/// Keep a Changelog's change types, in the order they are listed.
+const CHANGE_TYPES: [&str; 4] = ["Added", "Changed", "Fixed", "Security"];
+
+/// Inserts the release into the configured changelog under a heading for its version, replacing
+/// the section from an earlier run (and keeping its date), and leaving other releases as they
+/// are.
+pub fn update(summary: &ReleaseSummary, issues: &IssueData) -> Result<()> {
+    let settings = &config().changelog;
+    let issues = match &settings.edition {
+        Some(edition) => Edition::selected(std::slice::from_ref(edition))?[0].apply(issues),
+        None => issues.clone(),
+    };
+
+    let changelog = match fs::read_to_string(&settings.path) {
+        Ok(changelog) => changelog,
+        Err(e) if e.kind() == ErrorKind::NotFound => HEADER.to_string(),
+        Err(e) => return Err(eyre!("Unable to read {}: {}", settings.path.display(), e)),
+    };
+    let changelog = insert(
+        &changelog,
+        &summary.key,
+        summary.released_at,
+        &issues,
+        settings,
+    )?;
+
+    fs::write(&settings.path, changelog)?;
+    info!(
+        "Added {} to the changelog in {}",
+        summary.key,
+        settings.path.display()
+    );
+    Ok(())
+}
+
+/// The changelog with the release's section in it, in place of the section from an earlier run
+/// or else above the first older release.
+fn insert(
+    changelog: &str,
+    version: &str,
+    released_at: NaiveDate,
+    issues: &IssueData,
+    settings: &ChangelogConfig,
+) -> Result<String> {
+    let mut lines: Vec<&str> = changelog.lines().collect();
+
+    let version_heading = Regex::new(r"^## \[([^\]]+)\](?: - (\d{4}-\d{2}-\d{2}))?")?;
+    // Link reference definitions such as `[1.2]: https://...` follow the last section.
+    let link_reference = Regex::new(r"^\[[^\]]+\]: ")?;
+    let section_end = |line: &&str| line.starts_with("## ") || link_reference.is_match(line);
+
+    let existing = lines.iter().position(|line| {
+        version_heading
+            .captures(line)
+            .is_some_and(|captures| captures[1] == *version)
+    });
+    let heading_date = |line: &str| {
+        version_heading
+            .captures(line)
+            .and_then(|captures| captures.get(2))
+            .and_then(|date| NaiveDate::parse_from_str(date.as_str(), "%Y-%m-%d").ok())
+    };
+    let released_at = existing
+        .and_then(|index| heading_date(lines[index]))
+        .unwrap_or(released_at);
+    let section = section(version, released_at, issues, settings);
+
+    let (start, end) = match existing {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(section_end)
+                .map_or(lines.len(), |offset| start + 1 + offset);
+            (start, end)
+        }
+        // Releases are kept newest first, below any Unreleased section, so one generated late
+        // goes above the first release made before it.
+        None => {
+            let position = lines
+                .iter()
+                .position(|line| {
+                    version_heading.captures(line).is_some_and(|captures| {
+                        &captures[1] != "Unreleased"
+                            && older(&captures[1], heading_date(line), version, released_at)
+                    })
+                })
+                .or_else(|| lines.iter().position(|line| link_reference.is_match(line)))
+                .unwrap_or(lines.len());
+            if position > 0 && !lines[position - 1].is_empty() {
+                lines.insert(position, "");
+                (position + 1, position + 1)
+            } else {
+                (position, position)
+            }
+        }
+    };
+    lines.splice(start..end, section.lines());
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+
+    let mut changelog = lines.join("\n");
+    changelog.push('\n');
+    Ok(changelog)
+}
+
+/// Whether the release under a heading was made before the new one: by date where the heading
+/// has one that differs, and otherwise by the numbers in the versions.
+fn older(
+    heading: &str,
+    heading_date: Option<NaiveDate>,
+    version: &str,
+    released_at: NaiveDate,
+) -> bool {
+    match heading_date {
+        Some(date) if date != released_at => date < released_at,
+        _ => version_numbers(heading) < version_numbers(version),
+    }
+}
+
+/// The numbers in a version such as `2.10` or `v1_v2`, so that `2.10` comes after `2.9`.
+fn version_numbers(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|number| number.parse().ok())
+        .collect()
+}
+
+/// The section for one release, ending with a blank line.
+fn section(
+    version: &str,
+    released_at: NaiveDate,
+    issues: &IssueData,
+    settings: &ChangelogConfig,
+) -> String {
+    let mut changes: [Vec<&Ticket>; 4] = Default::default();
+    for ticket in issues
+        .client_requests
+        .iter()
+        .chain(&issues.features)
+        .chain(&issues.bugfixes)
+    {
+        let index = if has_label(ticket, &settings.security_labels) {
+            3
+        } else if !ticket.feature {
+            2
+        } else if has_label(ticket, &settings.changed_labels) {
+            1
+        } else {
+            0
+        };
+        changes[index].push(ticket);
+    }
+
+    let mut section = format!("## [{}] - {}\n\n", version, released_at);
+    // Breaking changes are not one of Keep a Changelog's types, so they are listed ahead of them
+    // as well as under their own type.
+    let breaking = issues.breaking_changes();
+    if !breaking.is_empty() {
+        section.push_str("### Breaking Changes\n\n");
+        for ticket in breaking {
+            section.push_str(&format!(
+                "- {} ({})\n",
+                markdown_escape(&ticket.plain_description()),
+                ticket.reference()
+            ));
+            if let Some(notes) = ticket.plain_migration_notes() {
+                section.push_str(&format!("  {}\n", markdown_escape(&notes)));
+            }
+        }
+        section.push('\n');
+    }
+    for (change_type, tickets) in CHANGE_TYPES.iter().zip(&changes) {
+        if tickets.is_empty() {
+            continue;
+        }
+        section.push_str(&format!("### {}\n\n", change_type));
+        let mut tickets = tickets.clone();
+        tickets.sort_by_key(|ticket| ticket.number);
+        for ticket in tickets {
+            section.push_str(&format!(
+                "- {} ({})\n",
+                markdown_escape(&ticket.plain_description()),
+                ticket.reference()
+            ));
+        }
+        section.push('\n');
+    }
+
+    section
+}
+
+/// Escapes the characters which would otherwise start emphasis, links or code in a list item.
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '[' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn has_label(ticket: &Ticket, labels: &[String]) -> bool {
+    ticket
+        .labels
+        .iter()
+        .any(|label| labels.iter().any(|l| l.eq_ignore_ascii_case(label)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    fn issues(title: &str) -> IssueData {
+        IssueData {
+            bugfixes: vec![Ticket::fixture(1, title)],
+            ..IssueData::default()
+        }
+    }
+
+    #[test]
+    fn rerunning_a_release_leaves_the_changelog_as_it_was() {
+        let settings = ChangelogConfig::default();
+        let issues = issues("Fix the bid table");
+
+        let once = insert(HEADER, "2.0", date(1), &issues, &settings).unwrap();
+        let once = insert(&once, "2.1", date(8), &issues, &settings).unwrap();
+        let twice = insert(&once, "2.0", date(9), &issues, &settings).unwrap();
+
+        assert_eq!(twice, once);
+        assert!(once.contains("## [2.0] - 2025-03-01\n\n### Fixed\n\n- Fix the bid table (#1)\n"));
+    }
+
+    #[test]
+    fn inserts_late_releases_below_newer_ones() {
+        let settings = ChangelogConfig::default();
+        let changelog = format!(
+            "{}\n## [Unreleased]\n\n## [2.2] - 2025-03-15\n\n## [2.0] - 2025-03-01\n\n[2.2]: https://example.com/2.2\n",
+            HEADER
+        );
+
+        let changelog = insert(&changelog, "2.1", date(8), &issues("Fix"), &settings).unwrap();
+        let headings: Vec<&str> = changelog
+            .lines()
+            .filter(|line| line.starts_with("## "))
+            .collect();
+
+        assert_eq!(
+            headings,
+            [
+                "## [Unreleased]",
+                "## [2.2] - 2025-03-15",
+                "## [2.1] - 2025-03-08",
+                "## [2.0] - 2025-03-01",
+            ]
+        );
+    }
+
+    #[test]
+    fn orders_releases_made_the_same_day_by_version() {
+        let settings = ChangelogConfig::default();
+        let changelog = insert(HEADER, "2.10", date(1), &issues("Fix"), &settings).unwrap();
+        let changelog = insert(&changelog, "2.9", date(1), &issues("Fix"), &settings).unwrap();
+
+        assert!(changelog.find("## [2.10]").unwrap() < changelog.find("## [2.9]").unwrap());
+    }
+
+    #[test]
+    fn escapes_markdown_in_entries() {
+        assert_eq!(
+            markdown_escape(r"Use *bold* in [links] with `code`, snake_case and C:\ paths"),
+            r"Use \*bold\* in \[links] with \`code\`, snake\_case and C:\\ paths"
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{
+    config::config,
+    escape,
+    issue_forms::Answers,
+    regex::{commit_footer_regexp, commit_header_regexp, merge_commit_regexp},
+    IssueData, PrStats, Ticket,
+};
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::eyre, Result};
+use regex::Regex;
+use std::{collections::BTreeSet, path::Path, process::Command};
+use tracing::info;
+
+/// The label given to commits marked as breaking changes.
+const BREAKING_LABEL: &str = "breaking-change";
+
+/// One commit from `git log`.
+struct Commit {
+    hash: String,
+    author: String,
+    message: String,
+}
+
+/// The tickets and pull request figures of the Conventional Commits made after `from`, up to and
+/// including `to`, in the repository at `repository`. Features and bug fixes are told apart by
+/// the commit type, scopes stand in for modules, and pull requests are only known by the
+/// `(#123)` that squash merges leave on the subject line.
+pub async fn read(repository: &Path, from: &str, to: &str) -> Result<(IssueData, PrStats)> {
+    let header = commit_header_regexp().await?;
+    let footer = commit_footer_regexp().await?;
+    let settings = &config().commits;
+
+    let mut issues = IssueData::default();
+    let mut authors = BTreeSet::new();
+    let mut pulls = BTreeSet::new();
+    let mut skipped = 0;
+
+    for commit in log(repository, from, to)? {
+        let mut lines = commit.message.lines();
+        let Some(captures) = lines
+            .next()
+            .and_then(|subject| header.captures(subject.trim()))
+        else {
+            skipped += 1;
+            continue;
+        };
+
+        let kind = captures["kind"].to_lowercase();
+        let breaking_change = breaking_change(lines, footer);
+        let breaking = captures.name("breaking").is_some() || breaking_change.is_some();
+        let listed = |types: &[String]| types.iter().any(|t| t.eq_ignore_ascii_case(&kind));
+        let feature = if listed(&settings.feature_types) {
+            true
+        } else if listed(&settings.bugfix_types) {
+            false
+        } else if breaking {
+            true
+        } else {
+            skipped += 1;
+            continue;
+        };
+
+        let (description, number) = pull_reference(captures["description"].trim()).await?;
+        if number != 0 {
+            pulls.insert(number);
+        }
+        authors.insert(commit.author.clone());
+
+        let mut labels = vec![kind];
+        if breaking {
+            labels.push(BREAKING_LABEL.to_string());
+        }
+
+        let ticket = Ticket {
+            number,
+            title: escape(&capitalise(&description)),
+            detail: escape(&commit.author),
+            author: escape(&commit.author),
+            pulls: Vec::new(),
+            labels,
+            release_notes: None,
+            fields: Answers::new(),
+            modules: captures
+                .name("scope")
+                .map(|scope| scopes(scope.as_str()))
+                .unwrap_or_default(),
+            feature,
+            commit: Some(commit.hash),
+            breaking,
+            migration_notes: breaking_change.map(|note| escape(&note)),
+        };
+        issues.count_modules(&ticket, true);
+        if feature {
+            issues.features.push(ticket);
+        } else {
+            issues.bugfixes.push(ticket);
+        }
+    }
+
+    if issues.features.is_empty() && issues.bugfixes.is_empty() {
+        return Err(eyre!(
+            "No feature or fix commits found between {} and {}",
+            from,
+            to
+        ));
+    }
+    if skipped > 0 {
+        info!(
+            "Left out {} commits which were not features, fixes or breaking changes",
+            skipped
+        );
+    }
+
+    issues.released_at = Some(committed_at(repository, to)?);
+
+    let pull_stats = PrStats {
+        total_count: pulls.len(),
+        contributor_count: authors.len(),
+        ..PrStats::default()
+    };
+    Ok((issues, pull_stats))
+}
+
+/// The description without the `(#123)` a squash merge leaves at its end, and the number of that
+/// pull request, or 0 if it has none.
+async fn pull_reference(description: &str) -> Result<(String, u64)> {
+    let squash = merge_commit_regexp().await?;
+    match squash
+        .captures(description)
+        .and_then(|c| c.get(0).zip(c.get(2)))
+    {
+        Some((reference, number)) => Ok((
+            description[..reference.start()].trim_end().to_string(),
+            number.as_str().parse()?,
+        )),
+        None => Ok((description.to_string(), 0)),
+    }
+}
+
+/// The non-merge commits after `from` up to `to`, oldest first.
+fn log(repository: &Path, from: &str, to: &str) -> Result<Vec<Commit>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repository)
+        .args([
+            "log",
+            "--no-merges",
+            "--reverse",
+            "--format=%H%x1f%an%x1f%B%x1e",
+        ])
+        .arg(format!("{}..{}", from, to))
+        .output()
+        .map_err(|e| eyre!("Unable to run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to read the commits in {}, with the following stderr: {}",
+            repository.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut parts = record.trim_start().splitn(3, '\x1f');
+            Some(Commit {
+                hash: parts.next()?.to_string(),
+                author: parts.next()?.to_string(),
+                message: parts.next()?.replace("\r\n", "\n"),
+            })
+        })
+        .collect())
+}
+
+/// When `rev` was committed in the repository at `repository`.
+pub fn committed_at(repository: &Path, rev: &str) -> Result<DateTime<Utc>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repository)
+        .args(["log", "-1", "--format=%cI", rev])
+        .output()
+        .map_err(|e| eyre!("Unable to run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Failed to read the date of {} in {}, with the following stderr: {}",
+            rev,
+            repository.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let date = String::from_utf8_lossy(&output.stdout);
+    Ok(DateTime::parse_from_rfc3339(date.trim())?.with_timezone(&Utc))
+}
+
+/// The footer tokens which mark a breaking change, with either separator.
+const BREAKING_FOOTERS: [&str; 4] = [
+    "BREAKING CHANGE:",
+    "BREAKING-CHANGE:",
+    "BREAKING CHANGE #",
+    "BREAKING-CHANGE #",
+];
+
+/// The text of a `BREAKING CHANGE:` or `BREAKING CHANGE #` footer, which runs until the next
+/// footer.
+fn breaking_change<'a>(lines: impl Iterator<Item = &'a str>, footer: &Regex) -> Option<String> {
+    let mut note: Option<Vec<&str>> = None;
+    for line in lines {
+        let text = BREAKING_FOOTERS
+            .iter()
+            .find_map(|footer| line.strip_prefix(footer));
+        match (&mut note, text) {
+            (_, Some(text)) => note = Some(vec![text.trim()]),
+            (Some(_), None) if footer.is_match(line) => break,
+            (Some(note), None) => note.push(line.trim()),
+            (None, None) => {}
+        }
+    }
+
+    note.map(|lines| lines.join("\n").trim().to_string())
+        .filter(|note| !note.is_empty())
+}
+
+/// The modules of a commit scope such as `api` or `api,ui`.
+fn scopes(scope: &str) -> Vec<String> {
+    let names = &config().commits.scopes;
+    scope
+        .split(',')
+        .map(str::trim)
+        .filter(|scope| !scope.is_empty())
+        .map(|scope| {
+            names
+                .get(scope)
+                .cloned()
+                .unwrap_or_else(|| scope.to_string())
+        })
+        .collect()
+}
+
+fn capitalise(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn parts(subject: &str) -> Option<(String, Option<String>, bool, String)> {
+        let captures = commit_header_regexp().await.unwrap().captures(subject)?;
+        Some((
+            captures["kind"].to_string(),
+            captures
+                .name("scope")
+                .map(|scope| scope.as_str().to_string()),
+            captures.name("breaking").is_some(),
+            captures["description"].to_string(),
+        ))
+    }
+
+    async fn note(message: &str) -> Option<String> {
+        breaking_change(
+            message.lines().skip(1),
+            commit_footer_regexp().await.unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn parses_subject_lines() {
+        assert_eq!(
+            parts("feat(api,ui)!: add bulk bids (#12)").await,
+            Some((
+                "feat".to_string(),
+                Some("api,ui".to_string()),
+                true,
+                "add bulk bids (#12)".to_string()
+            ))
+        );
+        assert_eq!(
+            parts("fix: round prices").await,
+            Some(("fix".to_string(), None, false, "round prices".to_string()))
+        );
+        assert_eq!(parts("Merge branch 'main'").await, None);
+        assert_eq!(parts("feat(api: unclosed scope").await, None);
+        assert_eq!(parts("feat:no space").await, None);
+    }
+
+    #[tokio::test]
+    async fn finds_squash_merge_pull_requests() {
+        assert_eq!(
+            pull_reference("add bids (#12)").await.unwrap(),
+            ("add bids".to_string(), 12)
+        );
+        assert_eq!(
+            pull_reference("fix #12 in bids").await.unwrap(),
+            ("fix #12 in bids".to_string(), 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_breaking_change_footers() {
+        assert_eq!(
+            note("feat: x\n\nBody.\n\nBREAKING CHANGE: bids need\na currency\nRefs: #4").await,
+            Some("bids need\na currency".to_string())
+        );
+        assert_eq!(
+            note("feat!: x\n\nBREAKING-CHANGE: the API is v2").await,
+            Some("the API is v2".to_string())
+        );
+        assert_eq!(
+            note("feat!: x\n\nBREAKING CHANGE #the API is v2\nCloses #3").await,
+            Some("the API is v2".to_string())
+        );
+        assert_eq!(note("feat!: x\n\nBREAKING CHANGE:").await, None);
+        assert_eq!(note("fix: x\n\nRefs: #4").await, None);
+    }
+}
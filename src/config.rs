@@ -0,0 +1,629 @@
+use color_eyre::{eyre::eyre, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use tokio::sync::OnceCell;
+use tracing::warn;
+
+static CONFIG: OnceCell<Config> = OnceCell::const_new();
+
+pub fn config() -> &'static Config {
+    CONFIG.get().expect("Config not initialized")
+}
+
+/// Settings read from `release_bot.toml` (or the file passed with `--config`).
+/// Every section is optional; a missing file behaves like an empty one.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The `owner/name` repositories whose milestones make up a release.
+    pub repositories: Vec<String>,
+    pub modules: ModuleConfig,
+    pub lint: LintConfig,
+    pub exclusions: ExclusionConfig,
+    pub carry_over: CarryOverConfig,
+    pub rollover: RolloverConfig,
+    pub history: HistoryConfig,
+    pub contributors: ContributorConfig,
+    pub delivery: DeliveryConfig,
+    /// Named report editions, such as `internal` and `customer`. Without any, a single report
+    /// with every section is generated.
+    pub editions: BTreeMap<String, EditionConfig>,
+    pub release_notes: ReleaseNotesConfig,
+    pub issue_forms: IssueFormsConfig,
+    pub overrides: OverridesConfig,
+    pub slack: SlackConfig,
+    /// Endpoints, such as Microsoft Teams, announced to with a JSON payload rendered from a
+    /// template, with `--publish`.
+    pub webhooks: Vec<WebhookConfig>,
+    pub feed: FeedConfig,
+    pub changelog: ChangelogConfig,
+    pub commits: CommitsConfig,
+    pub breaking: BreakingConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            repositories: vec!["marketdojo/auction".to_string()],
+            modules: ModuleConfig::default(),
+            lint: LintConfig::default(),
+            exclusions: ExclusionConfig::default(),
+            carry_over: CarryOverConfig::default(),
+            rollover: RolloverConfig::default(),
+            history: HistoryConfig::default(),
+            contributors: ContributorConfig::default(),
+            delivery: DeliveryConfig::default(),
+            editions: BTreeMap::new(),
+            release_notes: ReleaseNotesConfig::default(),
+            issue_forms: IssueFormsConfig::default(),
+            overrides: OverridesConfig::default(),
+            slack: SlackConfig::default(),
+            webhooks: Vec::new(),
+            feed: FeedConfig::default(),
+            changelog: ChangelogConfig::default(),
+            commits: CommitsConfig::default(),
+            breaking: BreakingConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModuleConfig {
+    /// Which module list feeds the statistics table.
+    pub source: ModuleSource,
+    /// Module name to the glob patterns of the files which belong to it.
+    pub paths: BTreeMap<String, Vec<String>>,
+    /// A CODEOWNERS-style file of `<pattern> <Module Name>` lines, used in
+    /// addition to `paths`.
+    pub codeowners: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleSource {
+    /// Only the answer to the "Which module(s)" question on the issue.
+    #[default]
+    Declared,
+    /// Modules derived from the files changed by linked pull requests,
+    /// falling back to the declared answer when nothing matched.
+    Inferred,
+    /// The union of the declared and inferred modules.
+    Both,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// Labels marking an issue as a feature, which should then carry a `[Feature]:`-style prefix.
+    pub feature_labels: Vec<String>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            feature_labels: vec!["feature".to_string(), "enhancement".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExclusionConfig {
+    /// Closed issues carrying any of these labels are not counted as shipped.
+    pub labels: Vec<String>,
+    /// Whether to list excluded issues in an appendix rather than dropping them silently.
+    pub appendix: bool,
+}
+
+impl Default for ExclusionConfig {
+    fn default() -> Self {
+        ExclusionConfig {
+            labels: vec![
+                "duplicate".to_string(),
+                "invalid".to_string(),
+                "wontfix".to_string(),
+            ],
+            appendix: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CarryOverConfig {
+    /// Whether to list the open issues still in the milestone as "Not Included in This Release".
+    pub section: bool,
+    /// Whether to add an internal appendix of every open issue and pull request, with assignees.
+    pub appendix: bool,
+}
+
+impl Default for CarryOverConfig {
+    fn default() -> Self {
+        CarryOverConfig {
+            section: true,
+            appendix: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RolloverConfig {
+    /// How the title of the next milestone is derived from the released one.
+    pub scheme: NamingScheme,
+    /// `strftime` format for milestone titles under the `date` scheme.
+    pub date_format: String,
+    /// Days between the released milestone's due date (or today) and the next one's.
+    pub due_in_days: i64,
+}
+
+impl Default for RolloverConfig {
+    fn default() -> Self {
+        RolloverConfig {
+            scheme: NamingScheme::Minor,
+            date_format: "%Y.%m.%d".to_string(),
+            due_in_days: 14,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NamingScheme {
+    /// Bump the first number of a `1.2.3`-style version in the title.
+    Major,
+    /// Bump the second number, resetting the third.
+    Minor,
+    /// Bump the third number, adding one if the title has only two.
+    Patch,
+    /// Title the milestone after its due date.
+    Date,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// Whether each generated release is recorded for trend reporting.
+    pub enabled: bool,
+    /// The SQLite database releases are recorded in.
+    pub database: PathBuf,
+    /// Whether reports compare their statistics with the previously recorded release.
+    pub compare_previous: bool,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            enabled: true,
+            database: PathBuf::from("releases/history.sqlite3"),
+            compare_previous: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContributorConfig {
+    /// Whether to include the per-author Contributors section.
+    pub section: bool,
+}
+
+impl Default for ContributorConfig {
+    fn default() -> Self {
+        ContributorConfig { section: true }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeliveryConfig {
+    /// Whether to include the Delivery Metrics section; needs the history database.
+    pub section: bool,
+    /// How many recorded releases, including the current one, the metrics cover.
+    pub window: usize,
+    /// The label marking a bug as introduced by an earlier release.
+    pub regression_label: String,
+    /// A regular expression matching the file names (e.g. `2.3.1`) of hotfix releases.
+    pub hotfix_pattern: String,
+    /// Where the metrics are written as JSON, if anywhere.
+    pub export: Option<PathBuf>,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        DeliveryConfig {
+            section: true,
+            window: 10,
+            regression_label: "regression".to_string(),
+            hotfix_pattern: r"^\d+\.\d+\.[1-9]\d*$".to_string(),
+            export: Some(PathBuf::from("releases/delivery_metrics.json")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReleaseNotesConfig {
+    /// The `###` heading in issue and pull request bodies whose text replaces the ticket title.
+    pub heading: String,
+}
+
+impl Default for ReleaseNotesConfig {
+    fn default() -> Self {
+        ReleaseNotesConfig {
+            heading: "Release notes".to_string(),
+        }
+    }
+}
+
+/// Reading answers out of issue bodies by the fields of the repository's issue forms, instead
+/// of by the hardcoded question headings. Fields are named by their `id`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IssueFormsConfig {
+    pub enabled: bool,
+    /// A local directory of issue forms, instead of the repository's `.github/ISSUE_TEMPLATE`.
+    pub path: Option<PathBuf>,
+    /// Fields naming the clients who requested the change, tried in order.
+    pub client_fields: Vec<String>,
+    /// Fields listing the modules affected, tried in order.
+    pub module_fields: Vec<String>,
+    /// Fields holding the release notes text, tried in order.
+    pub release_notes_fields: Vec<String>,
+    /// Field to the heading of an extra ticket table column showing its answer.
+    pub columns: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverridesConfig {
+    /// Where each release's `overrides-<release>.toml` (or `.yaml`) file is looked for.
+    pub directory: PathBuf,
+}
+
+impl Default for OverridesConfig {
+    fn default() -> Self {
+        OverridesConfig {
+            directory: PathBuf::from("releases"),
+        }
+    }
+}
+
+/// Announcing releases in a Slack channel through an incoming webhook, with `--publish`.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlackConfig {
+    /// The incoming webhook to post to; the `SLACK_WEBHOOK_URL` environment variable takes
+    /// precedence, so that the secret can be kept out of the file.
+    pub webhook_url: Option<String>,
+    /// The edition whose tickets are announced; the full report's if unset.
+    pub edition: Option<String>,
+    /// Link to the GitHub Release, with `{release}` replaced by the report's file stem.
+    pub release_url: Option<String>,
+    /// Link to the PDF report, with `{release}` replaced by the report's file stem.
+    pub pdf_url: Option<String>,
+    /// How many tickets each list shows before the rest are left to the full release notes;
+    /// 0 shows them all.
+    pub collapse_after: usize,
+}
+
+impl Default for SlackConfig {
+    fn default() -> Self {
+        SlackConfig {
+            webhook_url: None,
+            edition: None,
+            release_url: None,
+            pdf_url: None,
+            collapse_after: 10,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Identifies the webhook in logs and errors.
+    pub name: String,
+    /// The URL to POST to; give this or `url_env`.
+    pub url: Option<String>,
+    /// The environment variable holding the URL, so that the secret can be kept out of the file.
+    pub url_env: Option<String>,
+    /// A MiniJinja template rendering the JSON payload; a Teams Adaptive Card if unset.
+    pub template: Option<PathBuf>,
+    /// Extra request headers, such as for authentication.
+    pub headers: BTreeMap<String, String>,
+    /// The edition whose tickets are announced; the full report's if unset.
+    pub edition: Option<String>,
+    /// Link to the GitHub Release, with `{release}` replaced by the report's file stem.
+    pub release_url: Option<String>,
+    /// Link to the PDF report, with `{release}` replaced by the report's file stem.
+    pub pdf_url: Option<String>,
+    /// How many times a failed POST is retried, backing off between attempts.
+    pub retries: u32,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            name: String::new(),
+            url: None,
+            url_env: None,
+            template: None,
+            headers: BTreeMap::new(),
+            edition: None,
+            release_url: None,
+            pdf_url: None,
+            retries: 3,
+        }
+    }
+}
+
+/// The Atom feed of releases kept alongside the reports.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeedConfig {
+    /// Whether each generated release is added to the feed. Off by default, so no feed is
+    /// written until a release manager opts in.
+    pub enabled: bool,
+    pub path: PathBuf,
+    /// The edition whose tickets are listed; the full report's if unset.
+    pub edition: Option<String>,
+    /// Link for each entry, with `{release}` replaced by the report's file stem.
+    pub link: Option<String>,
+    /// How many of the most recent releases the feed keeps.
+    pub max_entries: usize,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        FeedConfig {
+            enabled: false,
+            path: PathBuf::from("releases/feed.xml"),
+            edition: None,
+            link: None,
+            max_entries: 50,
+        }
+    }
+}
+
+/// Adding each release to a changelog in the Keep a Changelog format.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChangelogConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    /// The edition whose tickets are listed; the full report's if unset.
+    pub edition: Option<String>,
+    /// Labels putting a feature under Changed rather than Added.
+    pub changed_labels: Vec<String>,
+    /// Labels putting a ticket under Security, whatever its kind.
+    pub security_labels: Vec<String>,
+}
+
+impl Default for ChangelogConfig {
+    fn default() -> Self {
+        ChangelogConfig {
+            enabled: false,
+            path: PathBuf::from("CHANGELOG.md"),
+            edition: None,
+            changed_labels: vec!["changed".to_string(), "enhancement".to_string()],
+            security_labels: vec!["security".to_string()],
+        }
+    }
+}
+
+/// Reading Conventional Commits from a local repository with `--git`, instead of issues from
+/// GitHub.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommitsConfig {
+    /// Commit types listed as features.
+    pub feature_types: Vec<String>,
+    /// Commit types listed as bug fixes. Commits of any other type are left out, unless they
+    /// are breaking changes, which are listed as features.
+    pub bugfix_types: Vec<String>,
+    /// Commit scope to the module it is counted against; other scopes are used as they are.
+    pub scopes: BTreeMap<String, String>,
+}
+
+impl Default for CommitsConfig {
+    fn default() -> Self {
+        CommitsConfig {
+            feature_types: vec!["feat".to_string()],
+            bugfix_types: vec!["fix".to_string(), "perf".to_string()],
+            scopes: BTreeMap::new(),
+        }
+    }
+}
+
+/// Marking tickets as breaking changes, which are listed first with their migration notes.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BreakingConfig {
+    /// Labels on an issue or a linked pull request marking it as a breaking change.
+    pub labels: Vec<String>,
+    /// Text in an issue or pull request title marking it as a breaking change, matched
+    /// case-insensitively.
+    pub title_markers: Vec<String>,
+    /// Issue form fields whose answer marks the issue as a breaking change, unless it is empty
+    /// or "No".
+    pub fields: Vec<String>,
+    /// Issue form fields holding the migration notes, tried in order.
+    pub migration_notes_fields: Vec<String>,
+    /// The `###` heading in issue and pull request bodies whose text is the migration notes.
+    pub migration_notes_heading: String,
+}
+
+impl Default for BreakingConfig {
+    fn default() -> Self {
+        BreakingConfig {
+            labels: vec!["breaking-change".to_string(), "breaking".to_string()],
+            title_markers: vec!["[Breaking]".to_string()],
+            fields: vec!["breaking_change".to_string()],
+            migration_notes_fields: vec!["migration_notes".to_string()],
+            migration_notes_heading: "Migration notes".to_string(),
+        }
+    }
+}
+
+/// A part of the report which editions can include or leave out.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    Breaking,
+    ClientRequests,
+    Features,
+    Bugfixes,
+    CarriedOver,
+    Statistics,
+    Delivery,
+    Contributors,
+    Rejected,
+    CarriedOverAppendix,
+}
+
+impl Section {
+    pub const ALL: [Section; 10] = [
+        Section::Breaking,
+        Section::ClientRequests,
+        Section::Features,
+        Section::Bugfixes,
+        Section::CarriedOver,
+        Section::Statistics,
+        Section::Delivery,
+        Section::Contributors,
+        Section::Rejected,
+        Section::CarriedOverAppendix,
+    ];
+}
+
+/// An optional column of the client request, feature and bug fix tables.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    /// Client Names, in the client requests table.
+    Clients,
+    /// Raised By, the GitHub login of the issue's author, in the other tables.
+    RaisedBy,
+    PullRequests,
+}
+
+impl Column {
+    pub const ALL: [Column; 3] = [Column::Clients, Column::RaisedBy, Column::PullRequests];
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditionConfig {
+    /// The sections this edition includes; sections disabled elsewhere stay disabled. Leaving out
+    /// `breaking` is warned about when the configuration is loaded.
+    pub sections: Vec<Section>,
+    /// The optional ticket table columns this edition includes.
+    pub columns: Vec<Column>,
+    /// Tickets and carried-over items carrying any of these labels are left out of this edition.
+    pub drop_labels: Vec<String>,
+    /// Label to the title shown instead of the real one for tickets and carried-over items
+    /// carrying it.
+    pub rewrite_labels: BTreeMap<String, String>,
+    /// Regular expressions whose matches in ticket and carried-over titles, client names, release
+    /// notes and migration notes are replaced with `[redacted]`.
+    pub redact: Vec<String>,
+    /// Whether to name authors "Contributor 1", "Contributor 2"... instead of by login.
+    pub anonymise_contributors: bool,
+}
+
+impl Default for EditionConfig {
+    fn default() -> Self {
+        EditionConfig {
+            sections: Section::ALL.to_vec(),
+            columns: Column::ALL.to_vec(),
+            drop_labels: Vec::new(),
+            rewrite_labels: BTreeMap::new(),
+            redact: Vec::new(),
+            anonymise_contributors: false,
+        }
+    }
+}
+
+pub fn init(path: &Path, required: bool) -> Result<&'static Config> {
+    let config: Config = match fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents)
+            .map_err(|e| eyre!("Invalid config file {}: {}", path.display(), e))?,
+        Err(e) if e.kind() == ErrorKind::NotFound && !required => Config::default(),
+        Err(e) => return Err(eyre!("Unable to read {}: {}", path.display(), e)),
+    };
+
+    if config.repositories.is_empty() {
+        return Err(eyre!("At least one repository must be configured"));
+    }
+    if let Some(repo) = config
+        .repositories
+        .iter()
+        .find(|repo| repo.split('/').count() != 2)
+    {
+        return Err(eyre!("Repository {} is not in owner/name form", repo));
+    }
+
+    if let Err(e) = regex::Regex::new(&config.delivery.hotfix_pattern) {
+        return Err(eyre!("Invalid delivery.hotfix_pattern: {}", e));
+    }
+
+    for (name, edition) in &config.editions {
+        for pattern in &edition.redact {
+            if let Err(e) = regex::Regex::new(pattern) {
+                return Err(eyre!("Invalid redact pattern in edition {}: {}", name, e));
+            }
+        }
+        if !edition.sections.contains(&Section::Breaking) {
+            warn!(
+                "Edition {} leaves out the breaking section, so its readers are not warned of breaking changes",
+                name
+            );
+        }
+    }
+
+    for (setting, edition) in [
+        ("slack.edition", &config.slack.edition),
+        ("feed.edition", &config.feed.edition),
+        ("changelog.edition", &config.changelog.edition),
+    ] {
+        if let Some(edition) = edition {
+            if !config.editions.contains_key(edition) {
+                return Err(eyre!("{} {} is not a configured edition", setting, edition));
+            }
+        }
+    }
+
+    for (index, webhook) in config.webhooks.iter().enumerate() {
+        if webhook.name.is_empty() {
+            return Err(eyre!("webhooks[{}] needs a name", index));
+        }
+        if webhook.url.is_some() == webhook.url_env.is_some() {
+            return Err(eyre!(
+                "Webhook {} needs exactly one of url or url_env",
+                webhook.name
+            ));
+        }
+        if let Some(edition) = &webhook.edition {
+            if !config.editions.contains_key(edition) {
+                return Err(eyre!(
+                    "Webhook {} uses edition {}, which is not configured",
+                    webhook.name,
+                    edition
+                ));
+            }
+        }
+    }
+
+    CONFIG
+        .set(config)
+        .map_err(|_| eyre!("Config already initialized"))?;
+
+    Ok(self::config())
+}
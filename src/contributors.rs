@@ -0,0 +1,339 @@
+use crate::{client, config::config, edition::Edition, escape, IssueData, OutputType, PrStats};
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use octocrab::models::{issues::Issue, pulls::Review};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::AddAssign,
+};
+
+/// GitHub rejects search queries longer than this.
+const MAX_QUERY_LENGTH: usize = 256;
+
+/// What one author contributed to a release.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Contribution {
+    pub pull_requests: usize,
+    /// Pull requests by other authors which this author reviewed.
+    pub reviews: usize,
+    /// Whether none of the author's pull requests were merged before this release.
+    pub first_time: bool,
+}
+
+impl AddAssign for Contribution {
+    /// Combines an author's contributions to several repositories. They are only a first-time
+    /// contributor if they were one in every repository where they authored pull requests.
+    fn add_assign(&mut self, other: Contribution) {
+        self.first_time = match (self.pull_requests, other.pull_requests) {
+            (0, _) => other.first_time,
+            (_, 0) => self.first_time,
+            _ => self.first_time && other.first_time,
+        };
+        self.pull_requests += other.pull_requests;
+        self.reviews += other.reviews;
+    }
+}
+
+/// Pull requests and reviews per author for the given closed pull requests, noting which
+/// authors had never had a pull request merged in `repo` before. `reviews` holds the reviews of
+/// each pull request by number.
+pub async fn contributions(
+    repo: &str,
+    pulls: &[Issue],
+    reviews: &HashMap<u64, Vec<Review>>,
+) -> Result<BTreeMap<String, Contribution>> {
+    let mut contributions: BTreeMap<String, Contribution> = BTreeMap::new();
+
+    for pull in pulls {
+        contributions
+            .entry(pull.user.login.clone())
+            .or_default()
+            .pull_requests += 1;
+
+        let reviewers: HashSet<&str> = reviews
+            .get(&pull.number)
+            .into_iter()
+            .flatten()
+            .filter_map(|review| review.user.as_ref().map(|user| user.login.as_str()))
+            .filter(|reviewer| *reviewer != pull.user.login)
+            .collect();
+
+        for reviewer in reviewers {
+            contributions
+                .entry(reviewer.to_string())
+                .or_default()
+                .reviews += 1;
+        }
+    }
+
+    let mut first_merges: BTreeMap<&str, DateTime<Utc>> = BTreeMap::new();
+    for pull in pulls {
+        if let Some(closed_at) = pull.closed_at {
+            let first = first_merges.entry(&pull.user.login).or_insert(closed_at);
+            *first = (*first).min(closed_at);
+        }
+    }
+    let released: HashSet<u64> = pulls.iter().map(|pull| pull.number).collect();
+    let returning = returning_authors(repo, &first_merges, &released).await?;
+
+    for (author, contribution) in contributions.iter_mut() {
+        if first_merges.contains_key(author.as_str()) {
+            contribution.first_time = !returning.contains(author);
+        }
+    }
+
+    Ok(contributions)
+}
+
+/// The authors who had a pull request merged in `repo` before their first in the release, given
+/// when that was. Authors are looked up together, in as few searches as GitHub's query length
+/// limit allows, as the Search API is heavily rate limited.
+async fn returning_authors(
+    repo: &str,
+    first_merges: &BTreeMap<&str, DateTime<Utc>>,
+    released: &HashSet<u64>,
+) -> Result<HashSet<String>> {
+    let mut returning = HashSet::new();
+    let mut undecided: Vec<(&str, DateTime<Utc>)> = first_merges
+        .iter()
+        .map(|(author, first_merge)| (*author, *first_merge))
+        .collect();
+
+    'search: while let Some((query, searched)) = earlier_merges_query(repo, &undecided) {
+        let mut page = client()
+            .search()
+            .issues_and_pull_requests(&query)
+            .per_page(100)
+            .send()
+            .await?;
+
+        loop {
+            let found: HashSet<String> = page
+                .items
+                .iter()
+                .filter(|pull| !released.contains(&pull.number))
+                .filter(|pull| {
+                    undecided.iter().any(|(author, first_merge)| {
+                        *author == pull.user.login
+                            && pull.closed_at.is_some_and(|closed| closed < *first_merge)
+                    })
+                })
+                .map(|pull| pull.user.login.clone())
+                .collect();
+
+            // The next search leaves out the authors already found, so needs fewer pages.
+            if !found.is_empty() {
+                undecided.retain(|(author, _)| !found.contains(*author));
+                returning.extend(found);
+                continue 'search;
+            }
+
+            match client().get_page(&page.next).await? {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
+
+        // None of the remaining authors in this query had merged anything before.
+        undecided.drain(..searched);
+    }
+
+    Ok(returning)
+}
+
+/// A search for pull requests merged before the latest first merge among the leading
+/// `undecided` authors, with as many of them as fit in one query, and how many that was. None once
+/// all are decided.
+fn earlier_merges_query(
+    repo: &str,
+    undecided: &[(&str, DateTime<Utc>)],
+) -> Option<(String, usize)> {
+    let prefix = format!("repo:{} is:pr is:merged merged:<", repo);
+    let mut length = prefix.len() + "YYYY-MM-DDTHH:MM:SSZ".len();
+    let mut authors = String::new();
+    let mut before = None;
+    let mut count = 0;
+
+    for (author, first_merge) in undecided {
+        let qualifier = format!(" author:\"{}\"", author);
+        if count > 0 && length + qualifier.len() > MAX_QUERY_LENGTH {
+            break;
+        }
+        length += qualifier.len();
+        authors.push_str(&qualifier);
+        before = before.max(Some(*first_merge));
+        count += 1;
+    }
+
+    let query = format!(
+        "{}{}{}",
+        prefix,
+        before?.format("%Y-%m-%dT%H:%M:%SZ"),
+        authors
+    );
+    Some((query, count))
+}
+
+/// The Contributors section, listing each author's pull requests, the tickets those closed and
+/// the reviews they gave. Editions with `anonymise_contributors` replace logins with numbered
+/// names.
+pub fn contributors_section(
+    issues: &IssueData,
+    pull_stats: &PrStats,
+    output_type: OutputType,
+    edition: &Edition,
+) -> String {
+    let settings = &config().contributors;
+    if !settings.section || pull_stats.contributors.is_empty() {
+        return String::new();
+    }
+
+    let tickets = issues
+        .client_requests
+        .iter()
+        .chain(&issues.features)
+        .chain(&issues.bugfixes);
+    let mut closed: BTreeMap<&str, usize> = BTreeMap::new();
+    for ticket in tickets {
+        let authors: HashSet<&str> = ticket.pulls.iter().map(|p| p.author.as_str()).collect();
+        for author in authors {
+            *closed.entry(author).or_default() += 1;
+        }
+    }
+
+    let mut authors: Vec<(&String, &Contribution)> = pull_stats.contributors.iter().collect();
+    authors.sort_by_key(|(_, c)| std::cmp::Reverse((c.pull_requests, c.reviews)));
+
+    let names: Vec<String> = authors
+        .iter()
+        .enumerate()
+        .map(|(i, (login, _))| {
+            if edition.anonymises_contributors() {
+                format!("Contributor {}", i + 1)
+            } else {
+                match output_type {
+                    OutputType::Latex => escape(login),
+                    OutputType::Markdown => format!("@{}", login),
+                }
+            }
+        })
+        .collect();
+
+    let rows = authors
+        .iter()
+        .zip(&names)
+        .map(|((login, c), name)| {
+            let issues_closed = closed.get(login.as_str()).copied().unwrap_or(0);
+            let first_time = if c.first_time { "Yes" } else { "" };
+            match output_type {
+                OutputType::Latex => format!(
+                    "{} & {} & {} & {} & {}",
+                    name, c.pull_requests, issues_closed, c.reviews, first_time
+                ),
+                OutputType::Markdown => format!(
+                    "| {} | {} | {} | {} | {} |",
+                    name, c.pull_requests, issues_closed, c.reviews, first_time
+                ),
+            }
+        })
+        .collect::<Vec<String>>();
+
+    let newcomers = authors
+        .iter()
+        .zip(&names)
+        .filter(|((_, c), _)| c.first_time)
+        .map(|(_, name)| name.as_str())
+        .collect::<Vec<&str>>();
+    let welcome = if newcomers.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "A warm welcome to our first-time contributors: {}.",
+            newcomers.join(", ")
+        )
+    };
+
+    match output_type {
+        OutputType::Latex => format!(
+            include_str!("../resources/contributors_section.tex.tmpl"),
+            contributor_table = rows.join(" \\\\\n"),
+            welcome = welcome
+        ),
+        OutputType::Markdown => format!(
+            include_str!("../resources/contributors_section.md.tmpl"),
+            contributor_table = rows.join("\n"),
+            welcome = welcome
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn contribution(pull_requests: usize, reviews: usize, first_time: bool) -> Contribution {
+        Contribution {
+            pull_requests,
+            reviews,
+            first_time,
+        }
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 1, hour, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn first_time_only_in_every_repository_with_pull_requests() {
+        let mut merged = contribution(2, 1, true);
+        merged += contribution(1, 0, false);
+        assert_eq!(
+            (merged.pull_requests, merged.reviews, merged.first_time),
+            (3, 1, false)
+        );
+
+        let mut merged = contribution(1, 0, true);
+        merged += contribution(2, 0, true);
+        assert!(merged.first_time);
+    }
+
+    #[test]
+    fn repositories_with_only_reviews_do_not_decide_first_time() {
+        let mut merged = contribution(0, 3, false);
+        merged += contribution(1, 0, true);
+        assert!(merged.first_time);
+
+        let mut merged = contribution(1, 0, true);
+        merged += contribution(0, 2, false);
+        assert!(merged.first_time);
+        assert_eq!(merged.reviews, 2);
+    }
+
+    #[test]
+    fn searches_before_the_latest_first_merge_with_quoted_authors() {
+        let (query, count) =
+            earlier_merges_query("owner/repo", &[("alice", at(9)), ("bob", at(14))]).unwrap();
+
+        assert_eq!(
+            query,
+            r#"repo:owner/repo is:pr is:merged merged:<2025-03-01T14:30:00Z author:"alice" author:"bob""#
+        );
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn splits_authors_across_queries_within_the_length_limit() {
+        let logins: Vec<String> = (0..20).map(|i| format!("contributor-{:02}", i)).collect();
+        let undecided: Vec<(&str, DateTime<Utc>)> =
+            logins.iter().map(|login| (login.as_str(), at(9))).collect();
+
+        let (query, count) = earlier_merges_query("owner/repo", &undecided).unwrap();
+        assert!(query.len() <= MAX_QUERY_LENGTH);
+        assert!(count > 1 && count < undecided.len());
+        assert!(query.ends_with(&format!("author:\"{}\"", logins[count - 1])));
+
+        assert!(earlier_merges_query("owner/repo", &[]).is_none());
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{client, duration_to_string, owner_and_name, OutputType};
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use octocrab::{
+    models::{
+        issues::Issue,
+        pulls::{Review, ReviewState},
+        repos::RepoCommit,
+    },
+    Page,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::AddAssign,
+};
+
+/// Percentiles reported for each cycle-time metric, matching the template's columns.
+const PERCENTILES: [usize; 3] = [50, 75, 90];
+
+/// A labelled set of samples and how to render one of its values.
+type Metric<'a> = (&'a str, &'a [i64], fn(i64) -> String);
+
+/// One sample per merged pull request for each stage of review, in seconds (or rounds). Samples
+/// are kept rather than summaries so that selections can be combined before percentiles are taken.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CycleTimes {
+    /// From opening the pull request to the first review by someone other than its author.
+    pub first_review: Vec<i64>,
+    /// The number of distinct commits which received a review.
+    pub review_rounds: Vec<i64>,
+    /// From the last approval to the merge.
+    pub approval_to_merge: Vec<i64>,
+    /// From the earliest authored commit to the merge.
+    pub first_commit_to_merge: Vec<i64>,
+    /// From the earliest authored commit to the release.
+    pub first_commit_to_release: Vec<i64>,
+}
+
+impl AddAssign for CycleTimes {
+    fn add_assign(&mut self, other: CycleTimes) {
+        self.first_review.extend(other.first_review);
+        self.review_rounds.extend(other.review_rounds);
+        self.approval_to_merge.extend(other.approval_to_merge);
+        self.first_commit_to_merge
+            .extend(other.first_commit_to_merge);
+        self.first_commit_to_release
+            .extend(other.first_commit_to_release);
+    }
+}
+
+/// Measures each merged pull request in `pulls`, using `reviews` (keyed by pull request number)
+/// and the commits API, up to the release made at `released_at`. Pull requests closed without
+/// merging are skipped.
+pub async fn cycle_times(
+    repo: &str,
+    pulls: &[Issue],
+    reviews: &HashMap<u64, Vec<Review>>,
+    released_at: DateTime<Utc>,
+) -> Result<CycleTimes> {
+    let (owner, name) = owner_and_name(repo);
+    let mut times = CycleTimes::default();
+
+    for pull in pulls {
+        let pull_request = client().pulls(owner, name).get(pull.number).await?;
+        let Some(merged_at) = pull_request.merged_at else {
+            continue;
+        };
+        let opened_at = pull_request.created_at.unwrap_or(pull.created_at);
+
+        let reviews: Vec<&Review> = reviews
+            .get(&pull.number)
+            .into_iter()
+            .flatten()
+            .filter(|review| {
+                review
+                    .user
+                    .as_ref()
+                    .is_some_and(|user| user.login != pull.user.login)
+            })
+            .filter(|review| review.submitted_at.is_some_and(|at| at <= merged_at))
+            .collect();
+
+        if let Some(first) = reviews.iter().filter_map(|r| r.submitted_at).min() {
+            times.first_review.push(seconds_between(opened_at, first));
+        }
+
+        let rounds: HashSet<&str> = reviews
+            .iter()
+            .filter_map(|review| review.commit_id.as_deref())
+            .collect();
+        times.review_rounds.push(rounds.len() as i64);
+
+        if let Some(approved) = reviews
+            .iter()
+            .filter(|review| review.state == Some(ReviewState::Approved))
+            .filter_map(|review| review.submitted_at)
+            .max()
+        {
+            times
+                .approval_to_merge
+                .push(seconds_between(approved, merged_at));
+        }
+
+        let page: Page<RepoCommit> = client()
+            .get(
+                format!("/repos/{}/pulls/{}/commits", repo, pull.number),
+                Some(&[("per_page", 100)]),
+            )
+            .await?;
+        if let Some(first_commit) = client()
+            .all_pages(page)
+            .await?
+            .into_iter()
+            .filter_map(|commit| commit.commit.author.and_then(|author| author.date))
+            .min()
+        {
+            times
+                .first_commit_to_merge
+                .push(seconds_between(first_commit, merged_at));
+            times
+                .first_commit_to_release
+                .push(seconds_between(first_commit, released_at));
+        }
+    }
+
+    Ok(times)
+}
+
+fn seconds_between(from: DateTime<Utc>, to: DateTime<Utc>) -> i64 {
+    (to - from).num_seconds().max(0)
+}
+
+/// The nearest-rank percentile of `samples`, or None if there are none.
+pub fn percentile(samples: &[i64], percentile: usize) -> Option<i64> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    let rank = (percentile * sorted.len()).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
+
+pub fn duration(seconds: i64) -> String {
+    match duration_to_string(chrono::Duration::seconds(seconds)) {
+        text if text.is_empty() => "under an hour".to_string(),
+        text => text,
+    }
+}
+
+impl CycleTimes {
+    /// The cycle-time table for the Statistics section, or nothing if no pull requests were merged.
+    pub fn table(&self, output_type: OutputType) -> String {
+        if self.review_rounds.is_empty() {
+            return String::new();
+        }
+
+        let metrics: [Metric<'_>; 4] = [
+            ("Time to first review", &self.first_review, duration),
+            ("Review rounds", &self.review_rounds, |n| n.to_string()),
+            ("Approval to merge", &self.approval_to_merge, duration),
+            (
+                "First commit to merge",
+                &self.first_commit_to_merge,
+                duration,
+            ),
+        ];
+
+        let rows = metrics
+            .iter()
+            .map(|(metric, samples, render)| {
+                let values = PERCENTILES
+                    .iter()
+                    .map(|p| {
+                        percentile(samples, *p)
+                            .map(render)
+                            .unwrap_or_else(|| "N/A".to_string())
+                    })
+                    .collect::<Vec<String>>();
+                match output_type {
+                    OutputType::Latex => format!("{} & {}", metric, values.join(" & ")),
+                    OutputType::Markdown => format!("| {} | {} |", metric, values.join(" | ")),
+                }
+            })
+            .collect::<Vec<String>>();
+
+        match output_type {
+            OutputType::Latex => format!(
+                include_str!("../resources/cycle_times.tex.tmpl"),
+                cycle_time_table = rows.join(" \\\\\n            ")
+            ),
+            OutputType::Markdown => format!(
+                include_str!("../resources/cycle_times.md.tmpl"),
+                cycle_time_table = rows.join("\n")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_the_nearest_rank() {
+        let samples = [15, 20, 35, 40, 50];
+        assert_eq!(percentile(&samples, 50), Some(35));
+        assert_eq!(percentile(&samples, 75), Some(40));
+        assert_eq!(percentile(&samples, 90), Some(50));
+        assert_eq!(percentile(&samples, 100), Some(50));
+    }
+
+    #[test]
+    fn sorts_the_samples_first() {
+        assert_eq!(percentile(&[9, 1, 5, 3, 7], 50), Some(5));
+    }
+
+    #[test]
+    fn uses_the_smallest_sample_for_the_zeroth_percentile() {
+        assert_eq!(percentile(&[4, 2, 8], 0), Some(2));
+    }
+
+    #[test]
+    fn has_no_percentile_without_samples() {
+        assert_eq!(percentile(&[], 50), None);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    config::config,
+    cycle_time::{duration, percentile},
+    history::{self, ReleaseSummary},
+    overrides::TicketOverride,
+    regex::hotfix_regexp,
+    OutputType,
+};
+use color_eyre::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::fs::{self, DirBuilder};
+
+/// DORA-style measures of how often and how safely releases reach production, taken over the
+/// releases recorded in the history database.
+#[derive(Debug, Serialize)]
+pub struct DeliveryMetrics {
+    /// Releases per week between the first and last release in the window.
+    pub deployment_frequency: Option<f64>,
+    /// The median of each release's lead time for changes, in seconds.
+    pub lead_time: Option<i64>,
+    /// The share of bugs fixed in a release which were regressions from the release before.
+    pub change_failure_rate: Option<f64>,
+    /// How long regressions fixed by hotfix releases had been open on average, in seconds.
+    pub time_to_restore: Option<i64>,
+    /// The releases measured, oldest first.
+    pub releases: Vec<ReleaseSummary>,
+    /// The overrides applied to the current release's tickets.
+    pub overrides: Vec<TicketOverride>,
+}
+
+impl DeliveryMetrics {
+    /// Measures `current` together with the releases recorded before it, noting the overrides
+    /// applied to it.
+    pub async fn new(current: ReleaseSummary, overrides: Vec<TicketOverride>) -> Result<Self> {
+        let mut releases =
+            history::preceding(&current, config().delivery.window.saturating_sub(1))?;
+        releases.push(current);
+
+        Ok(DeliveryMetrics::measure(
+            releases,
+            hotfix_regexp().await?,
+            overrides,
+        ))
+    }
+
+    /// Measures `releases`, oldest first, telling hotfix releases apart by their keys.
+    fn measure(
+        releases: Vec<ReleaseSummary>,
+        hotfix: &Regex,
+        overrides: Vec<TicketOverride>,
+    ) -> Self {
+        let days = match (releases.first(), releases.last()) {
+            (Some(first), Some(last)) => (last.released_at - first.released_at).num_days(),
+            _ => 0,
+        };
+        let deployment_frequency =
+            (days > 0).then(|| (releases.len() - 1) as f64 * 7.0 / days as f64);
+
+        let lead_times: Vec<i64> = releases
+            .iter()
+            .map(|r| r.lead_time)
+            .filter(|lead_time| *lead_time > 0)
+            .collect();
+
+        // Regressions are counted among the bug fixes, but overrides can move tickets out of
+        // them afterwards, so no release counts more failures than fixes.
+        let (failures, fixes) = releases.windows(2).fold((0, 0), |(failures, fixes), pair| {
+            let release = &pair[1];
+            (
+                failures + release.regressions.min(release.bugfixes),
+                fixes + release.bugfixes,
+            )
+        });
+        let change_failure_rate = (fixes > 0).then(|| failures as f64 / fixes as f64);
+
+        let restores: Vec<i64> = releases
+            .iter()
+            .filter(|r| hotfix.is_match(&r.key) && r.regressions > 0)
+            .map(|r| r.time_to_restore)
+            .collect();
+        let time_to_restore = restores
+            .iter()
+            .sum::<i64>()
+            .checked_div(restores.len() as i64);
+
+        DeliveryMetrics {
+            deployment_frequency,
+            lead_time: percentile(&lead_times, 50),
+            change_failure_rate,
+            time_to_restore,
+            releases,
+            overrides,
+        }
+    }
+
+    /// Writes the metrics as JSON to the configured export path, if there is one.
+    pub fn export(&self) -> Result<()> {
+        let Some(path) = &config().delivery.export else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            DirBuilder::new().recursive(true).create(parent)?;
+        }
+
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The Delivery Metrics section, or nothing if it is disabled.
+    pub fn section(&self, output_type: OutputType) -> String {
+        if !config().delivery.section {
+            return String::new();
+        }
+
+        let not_available = || "N/A".to_string();
+        let percent = match output_type {
+            OutputType::Latex => "\\%",
+            OutputType::Markdown => "%",
+        };
+        let first = &self.releases[0];
+        let last = &self.releases[self.releases.len() - 1];
+
+        let period = format!(
+            "{} releases from {} to {}",
+            self.releases.len(),
+            first.released_at,
+            last.released_at
+        );
+        let deployment_frequency = self
+            .deployment_frequency
+            .map(|frequency| format!("{:.1} releases per week", frequency))
+            .unwrap_or_else(not_available);
+        let lead_time = self.lead_time.map(duration).unwrap_or_else(not_available);
+        let change_failure_rate = self
+            .change_failure_rate
+            .map(|rate| format!("{:.0}{}", rate * 100.0, percent))
+            .unwrap_or_else(not_available);
+        let time_to_restore = self
+            .time_to_restore
+            .map(duration)
+            .unwrap_or_else(not_available);
+
+        match output_type {
+            OutputType::Latex => format!(
+                include_str!("../resources/delivery_section.tex.tmpl"),
+                period = period,
+                deployment_frequency = deployment_frequency,
+                lead_time = lead_time,
+                change_failure_rate = change_failure_rate,
+                time_to_restore = time_to_restore,
+            ),
+            OutputType::Markdown => format!(
+                include_str!("../resources/delivery_section.md.tmpl"),
+                period = period,
+                deployment_frequency = deployment_frequency,
+                lead_time = lead_time,
+                change_failure_rate = change_failure_rate,
+                time_to_restore = time_to_restore,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IssueData, PrStats};
+    use chrono::NaiveDate;
+
+    fn release(key: &str, day: u32) -> ReleaseSummary {
+        ReleaseSummary {
+            released_at: NaiveDate::from_ymd_opt(2025, 3, day).unwrap(),
+            ..ReleaseSummary::new(
+                key.to_string(),
+                key.to_string(),
+                &IssueData::default(),
+                &PrStats::default(),
+            )
+        }
+    }
+
+    fn fixes(mut release: ReleaseSummary, bugfixes: usize, regressions: usize) -> ReleaseSummary {
+        release.bugfixes = bugfixes;
+        release.regressions = regressions;
+        release
+    }
+
+    fn measure(releases: Vec<ReleaseSummary>) -> DeliveryMetrics {
+        let hotfix = Regex::new(r"^\d+\.\d+\.[1-9]\d*$").unwrap();
+        DeliveryMetrics::measure(releases, &hotfix, Vec::new())
+    }
+
+    #[test]
+    fn counts_releases_per_week_after_the_first() {
+        let metrics = measure(vec![
+            release("2.1.0", 1),
+            release("2.2.0", 8),
+            release("2.3.0", 15),
+        ]);
+
+        assert_eq!(metrics.deployment_frequency, Some(1.0));
+    }
+
+    #[test]
+    fn has_no_frequency_within_a_single_day() {
+        assert_eq!(
+            measure(vec![release("2.3.0", 1)]).deployment_frequency,
+            None
+        );
+        assert_eq!(
+            measure(vec![release("2.3.0", 1), release("2.3.1", 1)]).deployment_frequency,
+            None
+        );
+    }
+
+    #[test]
+    fn caps_failures_by_the_bugs_fixed_after_the_first_release() {
+        let metrics = measure(vec![
+            fixes(release("2.1.0", 1), 3, 3),
+            fixes(release("2.2.0", 8), 2, 5),
+            fixes(release("2.3.0", 15), 6, 1),
+        ]);
+
+        assert_eq!(metrics.change_failure_rate, Some(3.0 / 8.0));
+        assert_eq!(
+            measure(vec![fixes(release("2.1.0", 1), 3, 3)]).change_failure_rate,
+            None
+        );
+    }
+
+    #[test]
+    fn averages_time_to_restore_over_hotfixes_with_regressions() {
+        let mut releases = vec![
+            fixes(release("2.3.0", 1), 4, 2),
+            fixes(release("2.3.1", 3), 1, 1),
+            fixes(release("2.3.2", 5), 1, 0),
+            fixes(release("2.3.3", 7), 2, 2),
+        ];
+        for (release, time_to_restore) in releases.iter_mut().zip([50, 100, 200, 300]) {
+            release.time_to_restore = time_to_restore;
+        }
+
+        assert_eq!(measure(releases).time_to_restore, Some(200));
+        assert_eq!(
+            measure(vec![fixes(release("2.3.0", 1), 4, 2)]).time_to_restore,
+            None
+        );
+    }
+}
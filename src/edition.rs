@@ -0,0 +1,262 @@
+use crate::{
+    config::{config, Column, EditionConfig, Section},
+    IssueData,
+};
+use color_eyre::{eyre::eyre, Result};
+use regex::Regex;
+
+/// One version of the report, with its own sections, columns and redaction rules.
+pub struct Edition {
+    /// None for the single report generated when no editions are configured.
+    pub name: Option<String>,
+    settings: EditionConfig,
+    redactions: Vec<Regex>,
+}
+
+impl Edition {
+    /// The editions named, or every configured edition if none are; with no editions configured,
+    /// a single full report.
+    pub fn selected(names: &[String]) -> Result<Vec<Edition>> {
+        let editions = &config().editions;
+        if editions.is_empty() {
+            if let Some(name) = names.first() {
+                return Err(eyre!("No editions are configured, so {} is unknown", name));
+            }
+            return Ok(vec![Edition::new(None, EditionConfig::default())?]);
+        }
+
+        if names.is_empty() {
+            return editions
+                .iter()
+                .map(|(name, settings)| Edition::new(Some(name.clone()), settings.clone()))
+                .collect();
+        }
+
+        names
+            .iter()
+            .map(|name| {
+                let settings = editions
+                    .get(name)
+                    .ok_or_else(|| eyre!("No edition named {} is configured", name))?;
+                Edition::new(Some(name.clone()), settings.clone())
+            })
+            .collect()
+    }
+
+    fn new(name: Option<String>, settings: EditionConfig) -> Result<Self> {
+        let redactions = settings
+            .redact
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<Regex>, _>>()?;
+
+        Ok(Edition {
+            name,
+            settings,
+            redactions,
+        })
+    }
+
+    pub fn shows(&self, section: Section) -> bool {
+        self.settings.sections.contains(&section)
+    }
+
+    pub fn has_column(&self, column: Column) -> bool {
+        self.settings.columns.contains(&column)
+    }
+
+    pub fn anonymises_contributors(&self) -> bool {
+        self.settings.anonymise_contributors
+    }
+
+    /// Renders the section if this edition includes it.
+    pub fn render(&self, section: Section, render: impl FnOnce() -> String) -> String {
+        if self.shows(section) {
+            render()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Appended to report file names, so that editions do not overwrite each other.
+    pub fn file_suffix(&self) -> String {
+        self.name
+            .as_ref()
+            .map(|name| format!("-{}", name))
+            .unwrap_or_default()
+    }
+
+    /// The issues as this edition shows them: tickets and carried-over items with a dropped
+    /// label removed, titles rewritten, text redacted and module statistics recounted.
+    pub fn apply(&self, issues: &IssueData) -> IssueData {
+        let mut issues = issues.clone();
+
+        for tickets in [
+            &mut issues.client_requests,
+            &mut issues.features,
+            &mut issues.bugfixes,
+            &mut issues.rejected,
+        ] {
+            tickets.retain(|ticket| !self.drops(&ticket.labels));
+            for ticket in tickets.iter_mut() {
+                if let Some(title) = self.rewritten_title(&ticket.labels) {
+                    ticket.title = title;
+                    ticket.release_notes = None;
+                }
+                ticket.title = self.redact(&ticket.title);
+                ticket.detail = self.redact(&ticket.detail);
+                ticket.release_notes = ticket.release_notes.as_deref().map(|n| self.redact(n));
+                ticket.migration_notes = ticket.migration_notes.as_deref().map(|n| self.redact(n));
+            }
+        }
+
+        issues.carried_over.retain(|item| !self.drops(&item.labels));
+        for item in &mut issues.carried_over {
+            if let Some(title) = self.rewritten_title(&item.labels) {
+                item.title = title;
+            }
+            item.title = self.redact(&item.title);
+        }
+
+        issues.recount_modules();
+        issues
+    }
+
+    fn drops(&self, labels: &[String]) -> bool {
+        has_label(labels, &self.settings.drop_labels)
+    }
+
+    fn rewritten_title(&self, labels: &[String]) -> Option<String> {
+        self.settings
+            .rewrite_labels
+            .iter()
+            .find(|(label, _)| has_label(labels, std::slice::from_ref(*label)))
+            .map(|(_, title)| title.clone())
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.redactions
+            .iter()
+            .fold(text.to_string(), |text, pattern| {
+                pattern.replace_all(&text, "[redacted]").into_owned()
+            })
+    }
+}
+
+fn has_label(labels: &[String], wanted: &[String]) -> bool {
+    labels
+        .iter()
+        .any(|label| wanted.iter().any(|w| w.eq_ignore_ascii_case(label)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OpenItem, Ticket};
+
+    fn edition() -> Edition {
+        Edition::new(
+            Some("customer".to_string()),
+            EditionConfig {
+                drop_labels: vec!["internal".to_string()],
+                rewrite_labels: [("security".to_string(), "Security improvements".to_string())]
+                    .into(),
+                redact: vec![r"[\w.+-]+@example\.com".to_string()],
+                ..EditionConfig::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn ticket(number: u64, title: &str, labels: &[&str], module: &str) -> Ticket {
+        Ticket {
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+            modules: vec![module.to_string()],
+            ..Ticket::fixture(number, title)
+        }
+    }
+
+    fn open_item(number: u64, title: &str, labels: &[&str]) -> OpenItem {
+        OpenItem {
+            number,
+            title: title.to_string(),
+            author: "author".to_string(),
+            assignees: Vec::new(),
+            pull_request: false,
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+        }
+    }
+
+    fn issues() -> IssueData {
+        let mut issues = IssueData {
+            bugfixes: vec![
+                ticket(1, "Fix bids", &[], "Auctions"),
+                ticket(2, "Fix the admin panel", &["Internal"], "Admin"),
+                ticket(3, "Patch XSS in lot names", &["security"], "Auctions"),
+            ],
+            carried_over: vec![
+                open_item(4, "Tidy admin logs", &["internal"]),
+                open_item(5, "Rotate keys", &["security"]),
+                open_item(6, "Email jo@example.com", &[]),
+            ],
+            ..IssueData::default()
+        };
+        issues.recount_modules();
+        issues
+    }
+
+    #[test]
+    fn drops_and_rewrites_tickets_by_label() {
+        let issues = edition().apply(&issues());
+        let titles: Vec<&str> = issues.bugfixes.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["Fix bids", "Security improvements"]);
+    }
+
+    #[test]
+    fn drops_and_rewrites_carried_over_items_by_label() {
+        let issues = edition().apply(&issues());
+        let titles: Vec<&str> = issues
+            .carried_over
+            .iter()
+            .map(|i| i.title.as_str())
+            .collect();
+        assert_eq!(titles, ["Security improvements", "Email [redacted]"]);
+    }
+
+    #[test]
+    fn redacts_every_ticket_text() {
+        let issues = IssueData {
+            client_requests: vec![Ticket {
+                detail: "Acme (buyer@example.com)".to_string(),
+                release_notes: Some("Sent to ops@example.com".to_string()),
+                migration_notes: Some("Ask dev@example.com for a key".to_string()),
+                ..Ticket::fixture(1, "Report for buyer@example.com")
+            }],
+            ..IssueData::default()
+        };
+
+        let ticket = &edition().apply(&issues).client_requests[0];
+        assert_eq!(ticket.title, "Report for [redacted]");
+        assert_eq!(ticket.detail, "Acme ([redacted])");
+        assert_eq!(ticket.release_notes.as_deref(), Some("Sent to [redacted]"));
+        assert_eq!(
+            ticket.migration_notes.as_deref(),
+            Some("Ask [redacted] for a key")
+        );
+    }
+
+    #[test]
+    fn recounts_modules_from_the_remaining_tickets() {
+        let issues = edition().apply(&issues());
+        assert_eq!(issues.module_stats["Auctions"].bugs, 2);
+        assert!(!issues.module_stats.contains_key("Admin"));
+    }
+
+    #[test]
+    fn leaves_issues_alone_by_default() {
+        let edition = Edition::new(None, EditionConfig::default()).unwrap();
+        let issues = edition.apply(&issues());
+        assert_eq!(issues.bugfixes.len(), 3);
+        assert_eq!(issues.carried_over[2].title, "Email jo@example.com");
+    }
+}
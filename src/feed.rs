@@ -0,0 +1,293 @@
+use crate::{config::config, edition::Edition, history::ReleaseSummary, unescape, IssueData};
+use chrono::{DateTime, NaiveDate, Utc};
+use color_eyre::{eyre::eyre, Result};
+use regex::Regex;
+use std::{
+    fs::{self, DirBuilder},
+    io::ErrorKind,
+    path::Path,
+};
+use tracing::info;
+
+pub const TITLE: &str = "Market Dojo releases";
+
+/// One release in an Atom feed.
+pub struct Entry {
+    /// Stays the same when the release is regenerated, so readers do not see it twice.
+    pub id: String,
+    pub title: String,
+    /// When the release was made, which orders the feed.
+    pub published: DateTime<Utc>,
+    /// When the entry last changed.
+    pub updated: DateTime<Utc>,
+    pub link: Option<String>,
+    /// An HTML fragment.
+    pub content: String,
+}
+
+impl Entry {
+    /// An entry with the counts of the release's tickets, and its breaking changes, client
+    /// requests and features.
+    pub fn new(summary: &ReleaseSummary, issues: &IssueData, link: Option<String>) -> Entry {
+        let mut content = format!(
+            "<p>{} client requests, {} features and {} bug fixes.</p>",
+            issues.client_requests.len(),
+            issues.features.len(),
+            issues.bugfixes.len()
+        );
+        for (heading, tickets, migration_notes) in [
+            ("Breaking changes", issues.breaking_changes(), true),
+            (
+                "Client requests",
+                issues.client_requests.iter().collect(),
+                false,
+            ),
+            ("Features", issues.features.iter().collect(), false),
+        ] {
+            if tickets.is_empty() {
+                continue;
+            }
+            content.push_str(&format!("<h3>{}</h3><ul>", heading));
+            for ticket in tickets {
+                content.push_str(&format!(
+                    "<li>{} {}",
+                    ticket.reference(),
+                    xml_escape(&ticket.plain_description())
+                ));
+                if let Some(notes) = ticket.plain_migration_notes().filter(|_| migration_notes) {
+                    content.push_str(&format!("<br>Migration: {}", xml_escape(&notes)));
+                }
+                content.push_str("</li>");
+            }
+            content.push_str("</ul>");
+        }
+
+        Entry {
+            id: entry_id(&summary.key),
+            title: format!("Market Dojo {}", unescape(&summary.name)),
+            published: midnight(summary.released_at),
+            updated: midnight(summary.released_at),
+            link,
+            content,
+        }
+    }
+}
+
+/// The stable identifier of the release with this key.
+pub fn entry_id(key: &str) -> String {
+    format!("urn:release-bot:release:{}", key)
+}
+
+pub fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0)
+        .expect("Midnight is a valid time")
+        .and_utc()
+}
+
+/// An Atom feed of the entries, which should be newest first.
+pub fn render(title: &str, link: Option<&str>, entries: &[Entry]) -> String {
+    let updated = entries
+        .iter()
+        .map(|entry| entry.updated)
+        .max()
+        .unwrap_or_else(Utc::now);
+
+    format!(
+        include_str!("../resources/feed.xml.tmpl"),
+        title = xml_escape(title),
+        updated = updated.to_rfc3339(),
+        link = link_element(link, "  "),
+        entries = entries
+            .iter()
+            .map(|entry| format!(
+                include_str!("../resources/feed_entry.xml.tmpl"),
+                id = xml_escape(&entry.id),
+                title = xml_escape(&entry.title),
+                published = entry.published.to_rfc3339(),
+                updated = entry.updated.to_rfc3339(),
+                link = link_element(entry.link.as_deref(), "    "),
+                content = xml_escape(&entry.content),
+            ))
+            .collect::<String>(),
+    )
+}
+
+fn link_element(link: Option<&str>, indent: &str) -> String {
+    link.map(|link| format!("{}<link href=\"{}\"/>\n", indent, xml_escape(link)))
+        .unwrap_or_default()
+}
+
+/// Adds the release to the configured feed, replacing its entry from an earlier run. An entry
+/// whose content has not changed keeps its date, so rerunning leaves the feed as it was.
+pub fn update(summary: &ReleaseSummary, issues: &IssueData) -> Result<()> {
+    let settings = &config().feed;
+    let issues = match &settings.edition {
+        Some(edition) => Edition::selected(std::slice::from_ref(edition))?[0].apply(issues),
+        None => issues.clone(),
+    };
+    let link = settings
+        .link
+        .as_ref()
+        .map(|link| link.replace("{release}", &summary.key));
+
+    let mut entry = Entry::new(summary, &issues, link);
+    entry.updated = Utc::now();
+
+    let mut entries = read(&settings.path)?;
+    if let Some(index) = entries.iter().position(|e| e.id == entry.id) {
+        let existing = entries.remove(index);
+        if existing.title == entry.title
+            && existing.link == entry.link
+            && existing.content == entry.content
+        {
+            entry.updated = existing.updated;
+        }
+    }
+    entries.push(entry);
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.published));
+    entries.truncate(settings.max_entries);
+
+    if let Some(parent) = settings.path.parent() {
+        DirBuilder::new().recursive(true).create(parent)?;
+    }
+    fs::write(&settings.path, render(TITLE, None, &entries))?;
+    info!("Updated the feed in {}", settings.path.display());
+    Ok(())
+}
+
+/// The entries of a feed written by `render`; a missing file has none.
+fn read(path: &Path) -> Result<Vec<Entry>> {
+    let feed = match fs::read_to_string(path) {
+        Ok(feed) => feed,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(eyre!("Unable to read {}: {}", path.display(), e)),
+    };
+
+    let entry_regexp = Regex::new(r"(?s)<entry>(.*?)</entry>")?;
+    let element = |entry: &str, pattern: &str| -> Result<Option<String>> {
+        Ok(Regex::new(pattern)?
+            .captures(entry)
+            .map(|captures| xml_unescape(&captures[1])))
+    };
+
+    entry_regexp
+        .captures_iter(&feed)
+        .map(|captures| {
+            let entry = &captures[1];
+            let required = |pattern: &str, name: &str| {
+                element(entry, pattern)?
+                    .ok_or_else(|| eyre!("An entry in {} has no {}", path.display(), name))
+            };
+
+            let date = |text: &str| -> Result<DateTime<Utc>> {
+                Ok(DateTime::parse_from_rfc3339(text)?.with_timezone(&Utc))
+            };
+            let updated = date(&required(r"<updated>(.*?)</updated>", "updated date")?)?;
+
+            Ok(Entry {
+                id: required(r"<id>(.*?)</id>", "id")?,
+                title: required(r"<title>(.*?)</title>", "title")?,
+                published: match element(entry, r"<published>(.*?)</published>")? {
+                    Some(published) => date(&published)?,
+                    None => updated,
+                },
+                updated,
+                link: element(entry, r#"<link href="(.*?)"/>"#)?,
+                content: element(entry, r#"(?s)<content type="html">(.*?)</content>"#)?
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Escapes text for XML and HTML.
+pub fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&gt;", ">")
+        .replace("&lt;", "<")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_rendered(name: &str, feed: &str) -> Vec<Entry> {
+        let file = std::env::temp_dir().join(format!(
+            "release_bot_feed_{}_{}.xml",
+            std::process::id(),
+            name
+        ));
+        fs::write(&file, feed).unwrap();
+        let entries = read(&file).unwrap();
+        fs::remove_file(file).unwrap();
+        entries
+    }
+
+    fn date(day: u32) -> DateTime<Utc> {
+        midnight(NaiveDate::from_ymd_opt(2025, 3, day).unwrap())
+    }
+
+    #[test]
+    fn reads_back_rendered_entries() {
+        let entries = [
+            Entry {
+                id: entry_id("2.1"),
+                title: "Market Dojo 2.1 & <more>".to_string(),
+                published: date(2),
+                updated: date(5),
+                link: Some("https://releases.example.com/release-2.1.pdf?a=1&b=2".to_string()),
+                content: "<p>1 client requests, 0 features and 0 bug fixes.</p>\n<ul><li>\"Quoted\" &amp; escaped</li></ul>".to_string(),
+            },
+            Entry {
+                id: entry_id("2.0"),
+                title: "Market Dojo 2.0".to_string(),
+                published: date(1),
+                updated: date(1),
+                link: None,
+                content: String::new(),
+            },
+        ];
+
+        let feed = render(TITLE, Some("https://releases.example.com/"), &entries);
+        let read = read_rendered("round_trip", &feed);
+
+        assert_eq!(read.len(), entries.len());
+        for (read, entry) in read.iter().zip(&entries) {
+            assert_eq!(read.id, entry.id);
+            assert_eq!(read.title, entry.title);
+            assert_eq!(read.published, entry.published);
+            assert_eq!(read.updated, entry.updated);
+            assert_eq!(read.link, entry.link);
+            assert_eq!(read.content, entry.content);
+        }
+        assert_eq!(
+            render(TITLE, Some("https://releases.example.com/"), &read),
+            feed
+        );
+    }
+
+    #[test]
+    fn entries_without_a_published_date_use_the_updated_date() {
+        let feed = "<feed>\n  <entry>\n    <id>urn:release-bot:release:1.0</id>\n    <title>Market Dojo 1.0</title>\n    <updated>2025-03-04T00:00:00+00:00</updated>\n  </entry>\n</feed>\n";
+        let read = read_rendered("no_published", feed);
+
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].published, date(4));
+        assert_eq!(read[0].link, None);
+        assert_eq!(read[0].content, "");
+    }
+
+    #[test]
+    fn a_missing_feed_has_no_entries() {
+        let file = std::env::temp_dir().join("release_bot_feed_that_does_not_exist.xml");
+        assert!(read(&file).unwrap().is_empty());
+    }
+}
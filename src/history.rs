@@ -0,0 +1,470 @@
+use crate::{
+    config::config, cycle_time::percentile, duration_to_string, IssueData, OutputType, PrStats,
+};
+use chrono::{NaiveDate, Utc};
+use color_eyre::Result;
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use std::fs::DirBuilder;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS releases (
+    id INTEGER PRIMARY KEY,
+    key TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    released_at TEXT NOT NULL,
+    client_requests INTEGER NOT NULL,
+    features INTEGER NOT NULL,
+    bugfixes INTEGER NOT NULL,
+    average_lifetime INTEGER NOT NULL,
+    pull_requests INTEGER NOT NULL,
+    average_pr_lifetime INTEGER NOT NULL,
+    contributors INTEGER NOT NULL,
+    issue_data TEXT NOT NULL,
+    pr_stats TEXT NOT NULL
+);
+";
+
+/// Columns added since the table was first created, and added to older databases when opened.
+const ADDED_COLUMNS: [(&str, &str); 3] = [
+    ("regressions", "INTEGER NOT NULL DEFAULT 0"),
+    ("time_to_restore", "INTEGER NOT NULL DEFAULT 0"),
+    ("lead_time", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+const SUMMARY_COLUMNS: &str = "key, name, released_at, client_requests, features, bugfixes, \
+     average_lifetime, pull_requests, average_pr_lifetime, contributors, regressions, \
+     time_to_restore, lead_time";
+
+/// The headline figures of one recorded release.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReleaseSummary {
+    /// Identifies the release across reruns; the same as the report file name.
+    pub key: String,
+    pub name: String,
+    pub released_at: NaiveDate,
+    pub client_requests: usize,
+    pub features: usize,
+    pub bugfixes: usize,
+    pub average_lifetime: i64,
+    pub pull_requests: usize,
+    pub average_pr_lifetime: i64,
+    pub contributors: usize,
+    /// Shipped bug fixes labelled as regressions of an earlier release.
+    pub regressions: usize,
+    /// How long those regressions were open on average, in seconds.
+    pub time_to_restore: i64,
+    /// The median time from a merged pull request's first commit to the release, in seconds.
+    pub lead_time: i64,
+}
+
+impl ReleaseSummary {
+    pub fn new(key: String, name: String, issues: &IssueData, pull_stats: &PrStats) -> Self {
+        ReleaseSummary {
+            key,
+            name,
+            released_at: issues.released_at.unwrap_or_else(Utc::now).date_naive(),
+            client_requests: issues.client_requests.len(),
+            features: issues.features.len(),
+            bugfixes: issues.bugfixes.len(),
+            average_lifetime: issues.average_lifetime,
+            pull_requests: pull_stats.total_count,
+            average_pr_lifetime: pull_stats.average_lifetime,
+            contributors: pull_stats.contributor_count,
+            regressions: issues.regressions.len(),
+            time_to_restore: issues
+                .regressions
+                .iter()
+                .sum::<i64>()
+                .checked_div(issues.regressions.len() as i64)
+                .unwrap_or(0),
+            lead_time: percentile(&pull_stats.cycle_times.first_commit_to_release, 50).unwrap_or(0),
+        }
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(ReleaseSummary {
+            key: row.get(0)?,
+            name: row.get(1)?,
+            released_at: row.get(2)?,
+            client_requests: row.get(3)?,
+            features: row.get(4)?,
+            bugfixes: row.get(5)?,
+            average_lifetime: row.get(6)?,
+            pull_requests: row.get(7)?,
+            average_pr_lifetime: row.get(8)?,
+            contributors: row.get(9)?,
+            regressions: row.get(10)?,
+            time_to_restore: row.get(11)?,
+            lead_time: row.get(12)?,
+        })
+    }
+
+    /// A line for the Statistics section describing how this release differs from `previous`.
+    pub fn comparison(&self, previous: &ReleaseSummary, output_type: OutputType) -> String {
+        let lifetime_change = self.average_lifetime - previous.average_lifetime;
+        let lifetime = match lifetime_change {
+            change if change.abs() < 3600 => "about as long".to_string(),
+            change if change > 0 => format!(
+                "{} longer",
+                duration_to_string(chrono::Duration::seconds(change))
+            ),
+            change => format!(
+                "{} less",
+                duration_to_string(chrono::Duration::seconds(-change))
+            ),
+        };
+
+        let line = format!(
+            "Compared to {}: {} features, {} bug fixes, {} client requests, {} pull requests and {} authors; \
+             tickets existed for {} before being closed",
+            previous.name,
+            signed(self.features, previous.features),
+            signed(self.bugfixes, previous.bugfixes),
+            signed(self.client_requests, previous.client_requests),
+            signed(self.pull_requests, previous.pull_requests),
+            signed(self.contributors, previous.contributors),
+            lifetime
+        );
+
+        match output_type {
+            OutputType::Latex => format!("\\item {}", line),
+            OutputType::Markdown => format!("- {}", line),
+        }
+    }
+}
+
+fn signed(current: usize, previous: usize) -> String {
+    let change = current as i64 - previous as i64;
+    if change > 0 {
+        format!("+{}", change)
+    } else {
+        change.to_string()
+    }
+}
+
+fn open() -> Result<Connection> {
+    let path = &config().history.database;
+    if let Some(parent) = path.parent() {
+        DirBuilder::new().recursive(true).create(parent)?;
+    }
+
+    let connection = Connection::open(path)?;
+    migrate(&connection)?;
+    Ok(connection)
+}
+
+/// Creates the table, or adds the columns an older database is missing.
+fn migrate(connection: &Connection) -> Result<()> {
+    connection.execute_batch(SCHEMA)?;
+
+    for (column, definition) in ADDED_COLUMNS {
+        let exists = connection
+            .prepare("SELECT 1 FROM pragma_table_info('releases') WHERE name = ?1")?
+            .exists(params![column])?;
+        if !exists {
+            connection.execute_batch(&format!(
+                "ALTER TABLE releases ADD COLUMN {} {}",
+                column, definition
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Stores a release, replacing the figures (but not the date) of an earlier run of the same one.
+pub fn record(summary: &ReleaseSummary, issues: &IssueData, pull_stats: &PrStats) -> Result<()> {
+    insert(&open()?, summary, issues, pull_stats)
+}
+
+fn insert(
+    connection: &Connection,
+    summary: &ReleaseSummary,
+    issues: &IssueData,
+    pull_stats: &PrStats,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO releases (key, name, released_at, client_requests, features, bugfixes,
+             average_lifetime, pull_requests, average_pr_lifetime, contributors, regressions,
+             time_to_restore, lead_time, issue_data, pr_stats)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+         ON CONFLICT (key) DO UPDATE SET
+             name = excluded.name,
+             client_requests = excluded.client_requests,
+             features = excluded.features,
+             bugfixes = excluded.bugfixes,
+             average_lifetime = excluded.average_lifetime,
+             pull_requests = excluded.pull_requests,
+             average_pr_lifetime = excluded.average_pr_lifetime,
+             contributors = excluded.contributors,
+             regressions = excluded.regressions,
+             time_to_restore = excluded.time_to_restore,
+             lead_time = excluded.lead_time,
+             issue_data = excluded.issue_data,
+             pr_stats = excluded.pr_stats",
+        params![
+            summary.key,
+            summary.name,
+            summary.released_at,
+            summary.client_requests,
+            summary.features,
+            summary.bugfixes,
+            summary.average_lifetime,
+            summary.pull_requests,
+            summary.average_pr_lifetime,
+            summary.contributors,
+            summary.regressions,
+            summary.time_to_restore,
+            summary.lead_time,
+            serde_json::to_string(issues)?,
+            serde_json::to_string(pull_stats)?,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// The release made before `current`.
+pub fn previous(current: &ReleaseSummary) -> Result<Option<ReleaseSummary>> {
+    Ok(preceding(current, 1)?.pop())
+}
+
+/// Up to `count` releases made before `current`, oldest first. Releases are ordered by date, and
+/// those made the same day by when they were first recorded; a release already recorded keeps
+/// the date it was first recorded with.
+pub fn preceding(current: &ReleaseSummary, count: usize) -> Result<Vec<ReleaseSummary>> {
+    select_preceding(&open()?, current, count)
+}
+
+fn select_preceding(
+    connection: &Connection,
+    current: &ReleaseSummary,
+    count: usize,
+) -> Result<Vec<ReleaseSummary>> {
+    let mut statement = connection.prepare(&format!(
+        "WITH current AS (
+             SELECT COALESCE((SELECT released_at FROM releases WHERE key = ?1), ?2) AS released_at,
+                    COALESCE((SELECT id FROM releases WHERE key = ?1), 9223372036854775807) AS id
+         )
+         SELECT {} FROM (
+             SELECT releases.* FROM releases, current
+             WHERE releases.key != ?1
+               AND (releases.released_at < current.released_at
+                    OR (releases.released_at = current.released_at AND releases.id < current.id))
+             ORDER BY releases.released_at DESC, releases.id DESC LIMIT ?3
+         ) ORDER BY released_at, id",
+        SUMMARY_COLUMNS
+    ))?;
+    let releases = statement
+        .query_map(
+            params![current.key, current.released_at, count],
+            ReleaseSummary::from_row,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(releases)
+}
+
+/// The last `count` releases made, oldest first.
+pub fn recent(count: usize) -> Result<Vec<ReleaseSummary>> {
+    let connection = open()?;
+    let mut statement = connection.prepare(&format!(
+        "SELECT {} FROM (SELECT * FROM releases ORDER BY released_at DESC, id DESC LIMIT ?1)
+         ORDER BY released_at, id",
+        SUMMARY_COLUMNS
+    ))?;
+    let releases = statement
+        .query_map(params![count], ReleaseSummary::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(releases)
+}
+
+/// Every recorded release with its tickets, oldest first.
+pub fn recorded() -> Result<Vec<(ReleaseSummary, IssueData)>> {
+    let connection = open()?;
+    let mut statement = connection.prepare(&format!(
+        "SELECT {}, issue_data FROM releases ORDER BY released_at, id",
+        SUMMARY_COLUMNS
+    ))?;
+    let releases = statement
+        .query_map([], |row| {
+            Ok((ReleaseSummary::from_row(row)?, row.get::<_, String>(13)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    releases
+        .into_iter()
+        .map(|(summary, issues)| Ok((summary, serde_json::from_str(&issues)?)))
+        .collect()
+}
+
+const BAR_WIDTH: usize = 40;
+
+/// A labelled figure taken from each release, drawn as one bar per release in a chart.
+type Series<'a> = (&'a str, fn(&ReleaseSummary) -> i64);
+
+fn bar(value: i64, max: i64) -> String {
+    let length = if max > 0 {
+        (value.max(0) as usize * BAR_WIDTH).div_ceil(max as usize)
+    } else {
+        0
+    };
+    "█".repeat(length)
+}
+
+fn chart(title: &str, releases: &[ReleaseSummary], series: &[Series<'_>]) {
+    let max = releases
+        .iter()
+        .flat_map(|r| series.iter().map(move |(_, value)| value(r)))
+        .max()
+        .unwrap_or(0);
+    let name_width = releases.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    let label_width = series
+        .iter()
+        .map(|(label, _)| label.len())
+        .max()
+        .unwrap_or(0);
+
+    println!("\n{}", title);
+    for release in releases {
+        for (i, (label, value)) in series.iter().enumerate() {
+            let name = if i == 0 { release.name.as_str() } else { "" };
+            println!(
+                "  {:<name_width$}  {:<label_width$}  {} {}",
+                name,
+                label,
+                bar(value(release), max),
+                value(release)
+            );
+        }
+    }
+}
+
+/// Prints the recorded figures of the last `count` releases, with bar charts of how the
+/// balance of work, ticket lifetime and contributor count have changed.
+pub fn trends(count: usize) -> Result<i32> {
+    let releases = recent(count)?;
+    if releases.is_empty() {
+        println!(
+            "No releases recorded in {} yet",
+            config().history.database.display()
+        );
+        return Ok(0);
+    }
+
+    println!(
+        "{:<20} {:<10} {:>8} {:>8} {:>8} {:>6} {:>12}",
+        "Release", "Date", "Features", "Bugs", "Client", "PRs", "Contributors"
+    );
+    for release in &releases {
+        println!(
+            "{:<20} {:<10} {:>8} {:>8} {:>8} {:>6} {:>12}",
+            release.name,
+            release.released_at,
+            release.features,
+            release.bugfixes,
+            release.client_requests,
+            release.pull_requests,
+            release.contributors
+        );
+    }
+
+    chart(
+        "Bugs vs features",
+        &releases,
+        &[
+            ("features", |r| r.features as i64),
+            ("bugs", |r| r.bugfixes as i64),
+        ],
+    );
+    chart(
+        "Average ticket lifetime (days)",
+        &releases,
+        &[("days", |r| r.average_lifetime / 86400)],
+    );
+    chart(
+        "Contributors",
+        &releases,
+        &[("authors", |r| r.contributors as i64)],
+    );
+
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(key: &str, day: u32) -> ReleaseSummary {
+        ReleaseSummary {
+            released_at: NaiveDate::from_ymd_opt(2025, 3, day).unwrap(),
+            ..ReleaseSummary::new(
+                key.to_string(),
+                key.to_string(),
+                &IssueData::default(),
+                &PrStats::default(),
+            )
+        }
+    }
+
+    fn keys(releases: &[ReleaseSummary]) -> Vec<String> {
+        releases.iter().map(|r| r.key.clone()).collect()
+    }
+
+    #[test]
+    fn orders_releases_back_filled_out_of_order_by_date() {
+        let connection = Connection::open_in_memory().unwrap();
+        migrate(&connection).unwrap();
+        for release in [summary("1.0", 1), summary("1.2", 20), summary("1.1", 10)] {
+            insert(
+                &connection,
+                &release,
+                &IssueData::default(),
+                &PrStats::default(),
+            )
+            .unwrap();
+        }
+
+        let preceding = |release: &ReleaseSummary, count| {
+            keys(&select_preceding(&connection, release, count).unwrap())
+        };
+        assert_eq!(preceding(&summary("1.2", 20), 1), ["1.1"]);
+        assert_eq!(preceding(&summary("1.2", 20), 5), ["1.0", "1.1"]);
+        assert_eq!(preceding(&summary("1.1", 10), 5), ["1.0"]);
+        assert!(preceding(&summary("1.0", 1), 5).is_empty());
+        // New releases are placed by their own date.
+        assert_eq!(preceding(&summary("1.3", 25), 1), ["1.2"]);
+        assert_eq!(preceding(&summary("1.0.1", 5), 5), ["1.0"]);
+    }
+
+    #[test]
+    fn keeps_the_first_recorded_date_of_a_rerun_release() {
+        let connection = Connection::open_in_memory().unwrap();
+        migrate(&connection).unwrap();
+        for release in [summary("1.0", 1), summary("1.1", 10), summary("1.1", 30)] {
+            insert(
+                &connection,
+                &release,
+                &IssueData::default(),
+                &PrStats::default(),
+            )
+            .unwrap();
+        }
+        insert(
+            &connection,
+            &summary("1.2", 20),
+            &IssueData::default(),
+            &PrStats::default(),
+        )
+        .unwrap();
+
+        let preceding = select_preceding(&connection, &summary("1.2", 20), 5).unwrap();
+        assert_eq!(keys(&preceding), ["1.0", "1.1"]);
+        assert_eq!(
+            preceding[1].released_at,
+            NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use crate::{client, config::config, escape, owner_and_name};
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Where GitHub looks for issue forms in a repository.
+const FORMS_PATH: &str = ".github/ISSUE_TEMPLATE";
+
+// Only the parts of an issue form which shape the rendered issue body are read.
+#[derive(Deserialize)]
+struct IssueForm {
+    #[serde(default)]
+    body: Vec<FormElement>,
+}
+
+#[derive(Deserialize)]
+struct FormElement {
+    #[serde(rename = "type")]
+    kind: String,
+    id: Option<String>,
+    #[serde(default)]
+    attributes: FormAttributes,
+}
+
+#[derive(Default, Deserialize)]
+struct FormAttributes {
+    label: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FieldKind {
+    /// Inputs and textareas, answered in free text.
+    Text,
+    /// Answered with the selected options, separated by commas.
+    Dropdown,
+    /// Answered with a `- [X] option` or `- [ ] option` line per option.
+    Checkboxes,
+}
+
+/// A question on an issue form, which GitHub renders into the issue body as a `### <label>`
+/// heading followed by the answer.
+#[derive(Debug)]
+struct Field {
+    id: String,
+    label: String,
+    kind: FieldKind,
+}
+
+impl Field {
+    fn from_element(element: FormElement) -> Option<Field> {
+        let kind = match element.kind.as_str() {
+            "input" | "textarea" => FieldKind::Text,
+            "dropdown" => FieldKind::Dropdown,
+            "checkboxes" => FieldKind::Checkboxes,
+            _ => return None,
+        };
+        let label = element.attributes.label?.trim().to_string();
+
+        Some(Field {
+            id: element.id.unwrap_or_else(|| label.clone()),
+            label,
+            kind,
+        })
+    }
+
+    fn answer(&self, lines: &[&str]) -> Option<Answer> {
+        let text = lines.join("\n").trim().to_string();
+        if text.is_empty() || text == "_No response_" {
+            return None;
+        }
+
+        Some(match self.kind {
+            FieldKind::Text => Answer::Text(text),
+            FieldKind::Dropdown => Answer::Choices(text.split(", ").map(String::from).collect()),
+            FieldKind::Checkboxes => Answer::Choices(
+                text.lines()
+                    .filter_map(|line| {
+                        let line = line.trim();
+                        line.strip_prefix("- [X] ")
+                            .or_else(|| line.strip_prefix("- [x] "))
+                    })
+                    .map(|option| option.trim().to_string())
+                    .collect(),
+            ),
+        })
+    }
+}
+
+/// The answer to one issue form question.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Answer {
+    Text(String),
+    /// The selected dropdown options or ticked checkboxes.
+    Choices(Vec<String>),
+}
+
+impl Answer {
+    /// The answer as a list: the selected options, or the non-empty lines of the text.
+    pub fn items(&self) -> Vec<String> {
+        match self {
+            Answer::Text(text) => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect(),
+            Answer::Choices(choices) => choices.clone(),
+        }
+    }
+
+    /// The answer on one line, with the selected options or lines separated by commas.
+    pub fn text(&self) -> String {
+        self.items().join(", ")
+    }
+}
+
+/// Answers in an issue body, keyed by the id of the form field (or its label, if it has no id).
+pub type Answers = BTreeMap<String, Answer>;
+
+/// Reads answers out of issue bodies using the questions on a repository's issue forms.
+#[derive(Debug, Default)]
+pub struct FormParser {
+    fields: Vec<Field>,
+}
+
+impl FormParser {
+    /// Reads the issue forms from the configured directory, or from the repository itself.
+    /// Without `issue_forms.enabled`, the parser finds no answers.
+    pub async fn load(repo: &str) -> Result<FormParser> {
+        let settings = &config().issue_forms;
+        if !settings.enabled {
+            return Ok(FormParser::default());
+        }
+
+        let sources = match &settings.path {
+            Some(path) => local_forms(path)?,
+            None => repository_forms(repo).await?,
+        };
+
+        let mut fields = Vec::new();
+        for (name, source) in sources {
+            let form: IssueForm = serde_yaml::from_str(&source)
+                .map_err(|e| eyre!("Invalid issue form {}: {}", name, e))?;
+            fields.extend(form.body.into_iter().filter_map(Field::from_element));
+        }
+
+        Ok(FormParser { fields })
+    }
+
+    /// Every question answered in `body`. A heading only starts an answer if it matches the label
+    /// of a form field, so headings typed into free-text answers are kept as part of them.
+    pub fn answers(&self, body: &str) -> Answers {
+        let mut answers = Answers::new();
+        let mut current: Option<(&Field, Vec<&str>)> = None;
+
+        for line in body.lines() {
+            let field = line
+                .strip_prefix("### ")
+                .and_then(|heading| self.fields.iter().find(|f| f.label == heading.trim()));
+
+            match (field, &mut current) {
+                (Some(field), _) => {
+                    if let Some((previous, lines)) = current.replace((field, Vec::new())) {
+                        if let Some(answer) = previous.answer(&lines) {
+                            answers.insert(previous.id.clone(), answer);
+                        }
+                    }
+                }
+                (None, Some((_, lines))) => lines.push(line),
+                (None, None) => {}
+            }
+        }
+
+        if let Some((field, lines)) = current {
+            if let Some(answer) = field.answer(&lines) {
+                answers.insert(field.id.clone(), answer);
+            }
+        }
+
+        answers
+    }
+}
+
+fn is_form(name: &str) -> bool {
+    // config.yml configures the template chooser rather than being a form.
+    (name.ends_with(".yml") || name.ends_with(".yaml")) && !name.starts_with("config.")
+}
+
+fn local_forms(dir: &Path) -> Result<Vec<(String, String)>> {
+    let mut forms = Vec::new();
+
+    for entry in fs::read_dir(dir)
+        .map_err(|e| eyre!("Unable to read issue forms in {}: {}", dir.display(), e))?
+    {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if path.is_file() && is_form(name) {
+            forms.push((path.display().to_string(), fs::read_to_string(&path)?));
+        }
+    }
+
+    Ok(forms)
+}
+
+async fn repository_forms(repo: &str) -> Result<Vec<(String, String)>> {
+    let (owner, name) = owner_and_name(repo);
+    let listing = match client()
+        .repos(owner, name)
+        .get_content()
+        .path(FORMS_PATH)
+        .send()
+        .await
+    {
+        Ok(listing) => listing,
+        Err(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 404 => {
+            return Ok(Vec::new());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut forms = Vec::new();
+    for item in listing
+        .items
+        .into_iter()
+        .filter(|item| item.r#type == "file" && is_form(&item.name))
+    {
+        let file = client()
+            .repos(owner, name)
+            .get_content()
+            .path(&item.path)
+            .send()
+            .await?;
+        if let Some(source) = file
+            .items
+            .into_iter()
+            .next()
+            .and_then(|f| f.decoded_content())
+        {
+            forms.push((format!("{}/{}", repo, item.path), source));
+        }
+    }
+
+    Ok(forms)
+}
+
+/// The first answer among the given field ids.
+fn first_answer<'a>(answers: &'a Answers, ids: &[String]) -> Option<&'a Answer> {
+    ids.iter().find_map(|id| answers.get(id))
+}
+
+/// The requesting clients, from the configured client fields.
+pub fn clients(answers: &Answers) -> Option<String> {
+    first_answer(answers, &config().issue_forms.client_fields)
+        .map(|answer| escape(&answer.text()))
+        .filter(|clients| !clients.is_empty())
+}
+
+/// The affected modules, from the configured module fields.
+pub fn modules(answers: &Answers) -> Option<Vec<String>> {
+    first_answer(answers, &config().issue_forms.module_fields)
+        .map(Answer::items)
+        .filter(|modules| !modules.is_empty())
+}
+
+/// The user-facing description, from the configured release notes fields.
+pub fn release_notes(answers: &Answers) -> Option<String> {
+    first_answer(answers, &config().issue_forms.release_notes_fields).map(|answer| match answer {
+        Answer::Text(text) => text.clone(),
+        Answer::Choices(choices) => choices.join("\n"),
+    })
+}
+
+/// Whether one of the configured breaking change fields was answered with anything but "No",
+/// such as a ticked checkbox.
+pub fn breaking(answers: &Answers) -> bool {
+    config().breaking.fields.iter().any(|id| {
+        answers.get(id).is_some_and(|answer| {
+            let text = answer.text();
+            !text.is_empty()
+                && !["no", "none", "n/a", "_no response_"]
+                    .iter()
+                    .any(|no| text.eq_ignore_ascii_case(no))
+        })
+    })
+}
+
+/// How to adapt to a breaking change, from the configured migration notes fields.
+pub fn migration_notes(answers: &Answers) -> Option<String> {
+    first_answer(answers, &config().breaking.migration_notes_fields)
+        .map(|answer| answer.items().join("\n"))
+        .filter(|notes| !notes.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORM: &str = r#"
+name: Bug report
+body:
+  - type: markdown
+    attributes:
+      value: Thanks for reporting a bug!
+  - type: input
+    id: clients
+    attributes:
+      label: Which clients asked for this?
+  - type: dropdown
+    id: modules
+    attributes:
+      label: Which module(s) are affected?
+  - type: checkboxes
+    attributes:
+      label: Breaking change
+  - type: textarea
+    id: details
+    attributes:
+      label: Details
+"#;
+
+    fn parser() -> FormParser {
+        let form: IssueForm = serde_yaml::from_str(FORM).unwrap();
+        FormParser {
+            fields: form
+                .body
+                .into_iter()
+                .filter_map(Field::from_element)
+                .collect(),
+        }
+    }
+
+    const BODY: &str = "### Which clients asked for this?\n\nAcme, Globex\n\n\
+                        ### Which module(s) are affected?\n\nAuctions, SIM\n\n\
+                        ### Breaking change\n\n- [X] Changes the API\n- [ ] Needs a migration\n\n\
+                        ### Details\n\nSteps:\n### Not a question\n1. Bid";
+
+    #[test]
+    fn reads_answers_by_field_id_or_label() {
+        let answers = parser().answers(BODY);
+        assert_eq!(
+            answers.keys().collect::<Vec<_>>(),
+            ["Breaking change", "clients", "details", "modules"]
+        );
+    }
+
+    #[test]
+    fn splits_answers_by_field_kind() {
+        let answers = parser().answers(BODY);
+        assert!(matches!(&answers["clients"], Answer::Text(text) if text == "Acme, Globex"));
+        assert_eq!(answers["modules"].items(), ["Auctions", "SIM"]);
+        assert_eq!(answers["Breaking change"].items(), ["Changes the API"]);
+    }
+
+    #[test]
+    fn keeps_unknown_headings_in_free_text() {
+        let answers = parser().answers(BODY);
+        assert!(matches!(
+            &answers["details"],
+            Answer::Text(text) if text == "Steps:\n### Not a question\n1. Bid"
+        ));
+    }
+
+    #[test]
+    fn skips_unanswered_questions() {
+        let answers = parser()
+            .answers("### Which clients asked for this?\n\n_No response_\n\n### Details\n\n");
+        assert!(answers.is_empty());
+    }
+
+    #[test]
+    fn finds_nothing_without_forms() {
+        assert!(FormParser::default().answers(BODY).is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{
+    client, escape, marked_breaking, owner_and_name,
+    regex::{closing_references, migration_notes, release_notes},
+};
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use octocrab::{models::pulls::Review, Page};
+use serde::{Deserialize, Serialize};
+
+// octocrab's `TimelineEvent` keeps the cross-reference source private, so the
+// timeline is deserialized into these minimal shapes instead.
+#[derive(Deserialize)]
+struct TimelineItem {
+    event: Option<String>,
+    source: Option<TimelineSource>,
+}
+
+#[derive(Deserialize)]
+struct TimelineSource {
+    issue: Option<SourceIssue>,
+}
+
+#[derive(Deserialize)]
+struct SourceIssue {
+    number: u64,
+    title: String,
+    html_url: String,
+    body: Option<String>,
+    user: SourceUser,
+    #[serde(default)]
+    labels: Vec<SourceLabel>,
+    pull_request: Option<SourcePullRequest>,
+    repository: Option<SourceRepository>,
+}
+
+#[derive(Deserialize)]
+struct SourceUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct SourceLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct SourcePullRequest {
+    merged_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct SourceRepository {
+    full_name: String,
+}
+
+/// A merged pull request which resolved an issue.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkedPull {
+    pub number: u64,
+    pub url: String,
+    pub author: String,
+    pub merged_at: DateTime<Utc>,
+    /// The text under the release notes heading in the pull request's body, if any.
+    pub release_notes: Option<String>,
+    /// Whether a label or title marker flags the pull request as a breaking change.
+    #[serde(default)]
+    pub breaking: bool,
+    /// The text under the migration notes heading in the pull request's body, if any.
+    #[serde(default)]
+    pub migration_notes: Option<String>,
+}
+
+/// The merged pull requests in `repo` which closed the given issue.
+///
+/// Every pull request mentioning an issue leaves a cross-reference on its timeline. Those whose
+/// body uses a closing keyword (`Fixes #123`) are preferred; if none do, every merged
+/// cross-referencing pull request is returned, since links are often added by hand.
+pub async fn linked_pulls(repo: &str, issue_number: u64) -> Result<Vec<LinkedPull>> {
+    let page: Page<TimelineItem> = client()
+        .get(
+            format!("/repos/{}/issues/{}/timeline", repo, issue_number),
+            Some(&[("per_page", 100)]),
+        )
+        .await?;
+
+    let mut referencing: Vec<(bool, LinkedPull)> = Vec::new();
+
+    for issue in client()
+        .all_pages(page)
+        .await?
+        .into_iter()
+        .filter(|item| item.event.as_deref() == Some("cross-referenced"))
+        .filter_map(|item| item.source?.issue)
+        .filter(|issue| {
+            issue
+                .repository
+                .as_ref()
+                .is_some_and(|r| r.full_name.eq_ignore_ascii_case(repo))
+        })
+    {
+        let Some(merged_at) = issue.pull_request.and_then(|pr| pr.merged_at) else {
+            continue;
+        };
+        let body = issue.body.unwrap_or_default().replace("\r\n", "\n");
+        let closes = closing_references(&body).await.contains(&issue_number);
+        let labels: Vec<String> = issue.labels.into_iter().map(|label| label.name).collect();
+
+        referencing.push((
+            closes,
+            LinkedPull {
+                number: issue.number,
+                url: issue.html_url,
+                author: issue.user.login,
+                merged_at,
+                release_notes: release_notes(&body).await.map(|notes| escape(&notes)),
+                breaking: marked_breaking(&labels, &issue.title),
+                migration_notes: migration_notes(&body).await.map(|notes| escape(&notes)),
+            },
+        ));
+    }
+
+    referencing.sort_by_key(|(_, pull)| pull.number);
+    referencing.dedup_by_key(|(_, pull)| pull.number);
+
+    if referencing.iter().any(|(closes, _)| *closes) {
+        referencing.retain(|(closes, _)| *closes);
+    }
+
+    Ok(referencing.into_iter().map(|(_, pull)| pull).collect())
+}
+
+/// Paths of every file changed by the given pull request.
+pub async fn changed_files(repo: &str, pull_number: u64) -> Result<Vec<String>> {
+    let (owner, name) = owner_and_name(repo);
+    let page = client().pulls(owner, name).list_files(pull_number).await?;
+
+    Ok(client()
+        .all_pages(page)
+        .await?
+        .into_iter()
+        .map(|entry| entry.filename)
+        .collect())
+}
+
+/// Every review submitted on the given pull request, oldest first.
+pub async fn reviews(repo: &str, pull_number: u64) -> Result<Vec<Review>> {
+    let (owner, name) = owner_and_name(repo);
+    let page = client()
+        .pulls(owner, name)
+        .list_reviews(pull_number)
+        .per_page(100)
+        .send()
+        .await?;
+
+    Ok(client().all_pages(page).await?)
+}
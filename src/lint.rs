@@ -0,0 +1,336 @@
+use crate::{
+    body,
+    config::config,
+    get_issue,
+    issue_forms::{self, FormParser},
+    links::linked_pulls,
+    milestones::milestone_query,
+    regex::{closing_references, feature_regexp, module_answer},
+    search, search_closed, ClosedIssue,
+};
+use color_eyre::{eyre::eyre, Result};
+use octocrab::models::issues::Issue;
+use std::{collections::HashSet, fmt};
+
+/// Exit code used when the milestone has hygiene problems, distinct from the
+/// `1` returned when the bot itself fails.
+const PROBLEMS_FOUND: i32 = 2;
+
+#[derive(Debug, PartialEq)]
+enum Problem {
+    MissingModule,
+    UnprefixedFeature,
+    /// Closed as something other than completed, such as not planned.
+    NotShipped(String),
+    UnlinkedPull,
+    IssueOutsideMilestone {
+        issue: u64,
+        milestone: Option<String>,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingModule => write!(f, "issue does not say which module(s) it affects"),
+            Problem::UnprefixedFeature => {
+                write!(f, "issue has a feature label but no [Feature]: prefix")
+            }
+            Problem::NotShipped(reason) => write!(f, "issue was closed as {}", reason),
+            Problem::UnlinkedPull => write!(f, "pull request is not linked to any issue"),
+            Problem::IssueOutsideMilestone { issue, milestone } => match milestone {
+                Some(milestone) => {
+                    write!(f, "linked issue #{} is in milestone {}", issue, milestone)
+                }
+                None => write!(f, "linked issue #{} has no milestone", issue),
+            },
+        }
+    }
+}
+
+struct Finding {
+    number: u64,
+    title: String,
+    problem: Problem,
+}
+
+impl Finding {
+    fn new(item: &Issue, problem: Problem) -> Finding {
+        Finding {
+            number: item.number,
+            title: item.title.trim().to_string(),
+            problem,
+        }
+    }
+}
+
+/// Checks every given milestone for ticket hygiene problems, printing each one found.
+/// Returns a non-zero exit code if there were any, so that CI can gate a release on it.
+pub async fn run(versions: &[String]) -> Result<i32> {
+    if versions.is_empty() {
+        return Err(eyre!("At least one --milestone is required"));
+    }
+
+    let mut problem_count = 0;
+
+    for repo in &config().repositories {
+        for version in versions {
+            let findings = lint_milestone(version, repo).await?;
+            problem_count += findings.len();
+
+            println!(
+                "Milestone {} in {}: {} problem(s)",
+                version,
+                repo,
+                findings.len()
+            );
+            for finding in findings {
+                println!(
+                    "  #{} {}: {}",
+                    finding.number, finding.title, finding.problem
+                );
+            }
+        }
+    }
+
+    Ok(if problem_count > 0 { PROBLEMS_FOUND } else { 0 })
+}
+
+async fn lint_milestone(version: &str, repo: &str) -> Result<Vec<Finding>> {
+    let issues = search_closed(&milestone_query(version, repo, "is:closed is:issue")).await?;
+    let pulls = search(&milestone_query(version, repo, "is:pr is:merged")).await?;
+
+    let forms = FormParser::load(repo).await?;
+    let feature_labels = &config().lint.feature_labels;
+    let mut findings = Vec::new();
+    let mut linked = HashSet::new();
+
+    for closed in &issues {
+        let issue = &closed.issue;
+        let form_modules = issue_forms::modules(&forms.answers(&body(issue))).is_some();
+        for problem in issue_problems(closed, form_modules, feature_labels).await? {
+            findings.push(Finding::new(issue, problem));
+        }
+        if closed.unshipped_reason().is_some() {
+            continue;
+        }
+
+        linked.extend(
+            linked_pulls(repo, issue.number)
+                .await?
+                .into_iter()
+                .map(|pull| pull.number),
+        );
+    }
+
+    let milestone_issues: HashSet<u64> = issues.iter().map(|closed| closed.issue.number).collect();
+
+    for pull in &pulls {
+        let references = closing_references(&body(pull)).await;
+
+        if references.is_empty() && !linked.contains(&pull.number) {
+            findings.push(Finding::new(pull, Problem::UnlinkedPull));
+        }
+
+        for number in references {
+            if milestone_issues.contains(&number) {
+                continue;
+            }
+
+            // Read untyped, as octocrab cannot parse every reason an issue is closed for.
+            let issue = get_issue(repo, number).await?.issue;
+            if let Some(problem) = outside_milestone(&issue, version) {
+                findings.push(Finding::new(pull, problem));
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+/// The problems with a closed issue in the milestone: being closed without shipping, or else
+/// not naming its modules, in a form answer (`form_modules`) or the body, or lacking the prefix
+/// its feature labels call for.
+async fn issue_problems(
+    closed: &ClosedIssue,
+    form_modules: bool,
+    feature_labels: &[String],
+) -> Result<Vec<Problem>> {
+    let issue = &closed.issue;
+    if let Some(reason) = closed.unshipped_reason() {
+        return Ok(vec![Problem::NotShipped(reason.replace('_', " "))]);
+    }
+
+    let mut problems = Vec::new();
+    if !form_modules && module_answer(&body(issue)).await.is_none() {
+        problems.push(Problem::MissingModule);
+    }
+    if has_feature_label(issue, feature_labels) && !feature_regexp().await?.is_match(&issue.title) {
+        problems.push(Problem::UnprefixedFeature);
+    }
+
+    Ok(problems)
+}
+
+/// The problem with a pull request in milestone `version` closing `issue`, if the issue is in
+/// another milestone or none.
+fn outside_milestone(issue: &Issue, version: &str) -> Option<Problem> {
+    let milestone = issue.milestone.as_ref().map(|m| m.title.clone());
+
+    (issue.pull_request.is_none() && milestone.as_deref() != Some(version)).then_some(
+        Problem::IssueOutsideMilestone {
+            issue: issue.number,
+            milestone,
+        },
+    )
+}
+
+fn has_feature_label(issue: &Issue, feature_labels: &[String]) -> bool {
+    issue.labels.iter().any(|label| {
+        feature_labels
+            .iter()
+            .any(|feature| feature.eq_ignore_ascii_case(&label.name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn item(title: &str, body: &str, labels: &[&str]) -> Value {
+        let user = json!({
+            "login": "jo",
+            "id": 1,
+            "node_id": "U_1",
+            "avatar_url": "https://github.com/jo.png",
+            "gravatar_id": "",
+            "url": "https://api.github.com/users/jo",
+            "html_url": "https://github.com/jo",
+            "followers_url": "https://api.github.com/users/jo/followers",
+            "following_url": "https://api.github.com/users/jo/following",
+            "gists_url": "https://api.github.com/users/jo/gists",
+            "starred_url": "https://api.github.com/users/jo/starred",
+            "subscriptions_url": "https://api.github.com/users/jo/subscriptions",
+            "organizations_url": "https://api.github.com/users/jo/orgs",
+            "repos_url": "https://api.github.com/users/jo/repos",
+            "events_url": "https://api.github.com/users/jo/events",
+            "received_events_url": "https://api.github.com/users/jo/received_events",
+            "type": "User",
+            "site_admin": false,
+        });
+        let labels: Vec<Value> = labels
+            .iter()
+            .map(|name| {
+                json!({
+                    "id": 1,
+                    "node_id": "LA_1",
+                    "url": format!("https://api.github.com/repos/owner/repo/labels/{}", name),
+                    "name": name,
+                    "color": "ffffff",
+                    "default": false,
+                })
+            })
+            .collect();
+
+        json!({
+            "id": 1,
+            "node_id": "I_1",
+            "url": "https://api.github.com/repos/owner/repo/issues/7",
+            "repository_url": "https://api.github.com/repos/owner/repo",
+            "labels_url": "https://api.github.com/repos/owner/repo/issues/7/labels{/name}",
+            "comments_url": "https://api.github.com/repos/owner/repo/issues/7/comments",
+            "events_url": "https://api.github.com/repos/owner/repo/issues/7/events",
+            "html_url": "https://github.com/owner/repo/issues/7",
+            "number": 7,
+            "state": "closed",
+            "title": title,
+            "body": body,
+            "user": user,
+            "labels": labels,
+            "assignees": [],
+            "author_association": "MEMBER",
+            "locked": false,
+            "comments": 0,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-02T00:00:00Z",
+        })
+    }
+
+    fn closed(title: &str, body: &str, labels: &[&str], reason: &str) -> ClosedIssue {
+        let mut value = item(title, body, labels);
+        value["state_reason"] = json!(reason);
+        ClosedIssue::from_value(value).unwrap()
+    }
+
+    fn in_milestone(mut value: Value, milestone: &str) -> Issue {
+        value["milestone"] = json!({
+            "url": "https://api.github.com/repos/owner/repo/milestones/1",
+            "html_url": "https://github.com/owner/repo/milestone/1",
+            "id": 1,
+            "node_id": "MI_1",
+            "number": 1,
+            "title": milestone,
+            "created_at": "2024-01-01T00:00:00Z",
+        });
+        serde_json::from_value(value).unwrap()
+    }
+
+    const MODULES: &str = "### Which module(s) is this bug related to?\n\nBidding";
+
+    async fn problems(closed: &ClosedIssue, form_modules: bool) -> Vec<Problem> {
+        issue_problems(closed, form_modules, &["feature".to_string()])
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn reports_only_the_reason_for_issues_closed_without_shipping() {
+        let issue = closed("Add export", "", &["Feature"], "not_planned");
+        assert_eq!(
+            problems(&issue, false).await,
+            [Problem::NotShipped("not planned".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn needs_modules_from_a_form_or_the_body() {
+        let issue = closed("Fix the crash", "", &[], "completed");
+        assert_eq!(problems(&issue, false).await, [Problem::MissingModule]);
+        assert_eq!(problems(&issue, true).await, []);
+
+        let issue = closed("Fix the crash", MODULES, &[], "completed");
+        assert_eq!(problems(&issue, false).await, []);
+    }
+
+    #[tokio::test]
+    async fn needs_a_prefix_on_issues_labelled_as_features() {
+        let issue = closed("Add export", MODULES, &["Feature"], "completed");
+        assert_eq!(problems(&issue, false).await, [Problem::UnprefixedFeature]);
+
+        let issue = closed("[Feature]: Add export", MODULES, &["feature"], "completed");
+        assert_eq!(problems(&issue, false).await, []);
+    }
+
+    #[test]
+    fn flags_pull_requests_closing_issues_in_other_milestones() {
+        let issue = in_milestone(item("Fix the crash", "", &[]), "Release 1.2");
+        assert_eq!(outside_milestone(&issue, "Release 1.2"), None);
+        assert_eq!(
+            outside_milestone(&issue, "Release 1.3"),
+            Some(Problem::IssueOutsideMilestone {
+                issue: 7,
+                milestone: Some("Release 1.2".to_string()),
+            })
+        );
+
+        let issue: Issue = serde_json::from_value(item("Fix the crash", "", &[])).unwrap();
+        assert_eq!(
+            outside_milestone(&issue, "Release 1.3"),
+            Some(Problem::IssueOutsideMilestone {
+                issue: 7,
+                milestone: None,
+            })
+        );
+    }
+}
@@ -8,36 +8,198 @@
 
 //! This crate is a documentation generation crate for single releases of Market Dojo.
 
+mod changelog;
+mod commits;
+mod config;
+mod contributors;
+mod cycle_time;
+mod delivery;
+mod edition;
+mod feed;
+mod history;
+mod issue_forms;
+mod links;
+mod lint;
+mod milestones;
+mod modules;
+mod overrides;
 mod regex;
-
-use clap::Parser;
+mod review;
+mod selection;
+mod serve;
+mod site;
+mod slack;
+mod webhook;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
 use color_eyre::{eyre::eyre, Report, Result};
-use futures_util::TryStreamExt;
-use octocrab::{models::issues::Issue, Octocrab};
-use regex::{client_details, feature_regexp, module_details};
+use config::{config, Column, Section};
+use contributors::{contributions, contributors_section, Contribution};
+use cycle_time::{cycle_times, CycleTimes};
+use delivery::DeliveryMetrics;
+use edition::Edition;
+use history::ReleaseSummary;
+use issue_forms::{Answers, FormParser};
+use links::{linked_pulls, LinkedPull};
+use modules::{inference_enabled, inferred_modules, resolve_modules};
+use octocrab::{models::issues::Issue, Octocrab, Page};
+use overrides::{Overrides, TicketOverride};
+use regex::{client_details, feature_regexp, migration_notes, module_details, release_notes};
+use selection::Selection;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs::{DirBuilder, File},
     io::Write,
+    net::SocketAddr,
     ops::{Add, AddAssign},
+    path::PathBuf,
     process::Command,
     vec::IntoIter,
 };
-use tokio::{pin, sync::OnceCell};
-use tracing::{error, info};
+use tokio::sync::OnceCell;
+use tracing::{error, info, warn};
 
 static CLIENT: OnceCell<Octocrab> = OnceCell::const_new();
 fn client() -> &'static Octocrab {
     CLIENT.get().expect("Client not initialized")
 }
 
+/// A commit hash abbreviated as `git log --oneline` shows it.
+fn short_hash(hash: &str) -> String {
+    hash.chars().take(7).collect()
+}
+
+/// Splits an `owner/name` repository for the octocrab handlers which take the parts separately.
+fn owner_and_name(repo: &str) -> (&str, &str) {
+    repo.split_once('/')
+        .expect("Repositories are validated as owner/name when the config is loaded")
+}
+
+/// Whether GitHub answered 404, as it does for a ref or milestone a repository does not have.
+fn is_not_found(error: &octocrab::Error) -> bool {
+    matches!(error, octocrab::Error::GitHub { source, .. } if source.status_code.as_u16() == 404)
+}
+
 #[derive(Parser, Debug)]
 #[command(version, about)]
 struct Args {
-    #[clap(short, long)]
+    #[command(subcommand)]
+    command: Option<Commands>,
+    #[clap(short, long, global = true)]
     milestone: Vec<String>,
+    /// Use the most recently closed (or due) milestone instead of --milestone.
+    #[clap(long, global = true, conflicts_with = "milestone")]
+    latest: bool,
+    /// List the milestones in the configured repositories, with issue counts and due dates.
+    #[clap(long)]
+    list_milestones: bool,
+    /// Report on issues closed and pull requests merged on or after this date (YYYY-MM-DD),
+    /// instead of on a milestone.
+    #[clap(long, conflicts_with_all = ["milestone", "latest", "from_tag"])]
+    since: Option<NaiveDate>,
+    /// End of the --since range, inclusive; defaults to today.
+    #[clap(long, requires = "since")]
+    until: Option<NaiveDate>,
+    /// Report on pull requests merged after this tag, and the issues they closed.
+    #[clap(long, requires = "to_tag", conflicts_with_all = ["milestone", "latest"])]
+    from_tag: Option<String>,
+    /// End of the --from-tag range.
+    #[clap(long, requires = "from_tag")]
+    to_tag: Option<String>,
+    /// Report on the Conventional Commits in this local git repository instead of on GitHub
+    /// issues, from --from-ref to --to-ref.
+    #[clap(
+        long,
+        requires = "from_ref",
+        conflicts_with_all = ["milestone", "latest", "since", "from_tag"]
+    )]
+    git: Option<PathBuf>,
+    /// Start of the --git range; commits after it are reported.
+    #[clap(long, requires = "git")]
+    from_ref: Option<String>,
+    /// End of the --git range, inclusive; defaults to HEAD.
+    #[clap(long, requires = "git")]
+    to_ref: Option<String>,
+    /// File of manual corrections to ticket classification; defaults to
+    /// `releases/overrides-<release>.toml` (or `.yaml`) if that exists.
+    #[clap(long)]
+    overrides: Option<PathBuf>,
+    /// Review and correct the tickets in a terminal interface before the reports are rendered,
+    /// saving the decisions to the overrides file.
+    #[clap(long)]
+    review: bool,
+    /// Exit with status 2, before any report is written, if the release has breaking changes;
+    /// for patch releases.
+    #[clap(long)]
+    fail_on_breaking: bool,
+    /// Announce the release through the configured publishers once the reports are written.
+    #[clap(long, global = true)]
+    publish: bool,
+    /// Generate only this edition of the report; by default every configured edition is generated.
+    #[clap(long, global = true)]
+    edition: Vec<String>,
+    /// GitHub token; only --git reports, `site` and `trends` can run without one.
     #[clap(short, long, env = "GITHUB_TOKEN")]
-    token: String,
+    token: Option<String>,
+    /// Path to the TOML configuration file; optional unless given explicitly.
+    #[clap(short, long, global = true)]
+    config: Option<PathBuf>,
+}
+
+/// Without a subcommand, the release notes for the given milestones are generated.
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Report ticket hygiene problems in the given milestones, exiting non-zero if any are found
+    Lint,
+    /// Move the open issues and pull requests left in the given milestones to another milestone
+    CarryOver {
+        /// Title of the milestone to move them to
+        #[clap(long)]
+        to: String,
+        /// Print what would be moved without changing anything
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Close the given milestones, create the next one and move their open work into it
+    Rollover {
+        /// Title of the next milestone, instead of deriving it from the configured scheme
+        #[clap(long)]
+        next: Option<String>,
+        /// Due date (YYYY-MM-DD) of the next milestone, instead of the configured interval
+        #[clap(long)]
+        due: Option<NaiveDate>,
+        /// Print what would be done without changing anything
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Chart bugs vs features, ticket lifetime and contributors over the recorded releases
+    Trends {
+        /// How many of the most recent releases to include
+        #[clap(long, default_value_t = 10)]
+        last: usize,
+    },
+    /// Listen for GitHub milestone and release webhooks, generating the release notes for each
+    /// closed milestone or published release
+    Serve {
+        /// Address to listen on
+        #[clap(long, default_value = "127.0.0.1:3000")]
+        listen: SocketAddr,
+        /// The secret the webhook was created with, to verify its signatures
+        #[clap(long, env = "GITHUB_WEBHOOK_SECRET", hide_env_values = true)]
+        secret: String,
+    },
+    /// Build a static HTML site of every recorded or reported release, with module and client
+    /// pages and an Atom feed
+    Site {
+        /// Directory to write the site to
+        #[clap(long, default_value = "site")]
+        output: PathBuf,
+        /// URL the site will be served from, for absolute links in the feed
+        #[clap(long)]
+        base_url: Option<String>,
+    },
 }
 
 #[tokio::main]
@@ -47,14 +209,28 @@ async fn main() {
 
     let _ = CLIENT
         .get_or_try_init(|| async {
-            Ok::<Octocrab, Report>(Octocrab::builder().personal_token(args.token).build()?)
+            let builder = match &args.token {
+                Some(token) => Octocrab::builder().personal_token(token.clone()),
+                None => Octocrab::builder(),
+            };
+            Ok::<Octocrab, Report>(builder.build()?)
         })
         .await;
 
-    ::std::process::exit(match run(args.milestone).await {
-        Ok(_) => {
+    if let Err(err) = config::init(
+        args.config
+            .as_deref()
+            .unwrap_or_else(|| "release_bot.toml".as_ref()),
+        args.config.is_some(),
+    ) {
+        error!("Error occurred while loading configuration: {:?}", err);
+        ::std::process::exit(1);
+    }
+
+    ::std::process::exit(match dispatch(args).await {
+        Ok(code) => {
             info!("Goodbye");
-            0
+            code
         }
         Err(err) => {
             error!("Error occurred while running: {:?}", err);
@@ -63,6 +239,95 @@ async fn main() {
     });
 }
 
+async fn dispatch(args: Args) -> Result<i32> {
+    let has_token = args.token.is_some();
+    if args.list_milestones {
+        require_token(has_token)?;
+        return milestones::print_milestones().await;
+    }
+
+    match args.command {
+        Some(Commands::Trends { last }) => history::trends(last),
+        Some(Commands::Site { output, base_url }) => {
+            site::build(&output, &args.edition, base_url.as_deref())
+        }
+        Some(Commands::Serve { listen, secret }) => {
+            require_token(has_token)?;
+            serve::serve(listen, secret, args.edition, args.publish).await
+        }
+        Some(Commands::Lint) => {
+            require_token(has_token)?;
+            lint::run(&versions(args.latest, args.milestone).await?).await
+        }
+        Some(Commands::CarryOver { to, dry_run }) => {
+            require_token(has_token)?;
+            let versions = versions(args.latest, args.milestone).await?;
+            milestones::carry_over(&versions, &to, dry_run).await
+        }
+        Some(Commands::Rollover { next, due, dry_run }) => {
+            require_token(has_token)?;
+            let versions = versions(args.latest, args.milestone).await?;
+            milestones::rollover(&versions, next, due, dry_run).await
+        }
+        None => {
+            let selections = if let Some(since) = args.since {
+                require_token(has_token)?;
+                vec![Selection::DateRange {
+                    since,
+                    until: args.until.unwrap_or_else(|| Utc::now().date_naive()),
+                }]
+            } else if let (Some(from), Some(to)) = (args.from_tag, args.to_tag) {
+                require_token(has_token)?;
+                vec![Selection::TagRange { from, to }]
+            } else if let (Some(repository), Some(from)) = (args.git, args.from_ref) {
+                vec![Selection::Commits {
+                    repository,
+                    from,
+                    to: args.to_ref.unwrap_or_else(|| "HEAD".to_string()),
+                }]
+            } else {
+                require_token(has_token)?;
+                versions(args.latest, args.milestone)
+                    .await?
+                    .into_iter()
+                    .map(Selection::Milestone)
+                    .collect()
+            };
+
+            run(
+                selections,
+                &args.edition,
+                args.overrides,
+                args.review,
+                args.fail_on_breaking,
+                args.publish,
+            )
+            .await
+        }
+    }
+}
+
+/// Fails unless a GitHub token was given, which everything but `--git` reports, `site` and
+/// `trends` needs.
+fn require_token(has_token: bool) -> Result<()> {
+    if has_token {
+        Ok(())
+    } else {
+        Err(eyre!(
+            "A GitHub token is needed; pass --token or set GITHUB_TOKEN"
+        ))
+    }
+}
+
+/// The milestones given with `--milestone`, or the latest one with `--latest`.
+async fn versions(latest: bool, given: Vec<String>) -> Result<Vec<String>> {
+    if latest {
+        Ok(vec![milestones::latest_milestone().await?])
+    } else {
+        Ok(given)
+    }
+}
+
 fn duration_to_string(duration: chrono::Duration) -> String {
     let mut components = Vec::new();
     let years = duration.num_days() / 365;
@@ -94,7 +359,7 @@ fn duration_to_string(duration: chrono::Duration) -> String {
     components.join(", ")
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 struct ModuleStat {
     bugs: usize,
     features: usize,
@@ -117,18 +382,264 @@ impl AddAssign for ModuleStat {
     }
 }
 
+#[derive(Clone, Copy)]
 enum OutputType {
     Latex,
     Markdown,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Ticket {
+    number: u64,
+    title: String,
+    /// Client names for client requests, otherwise the login of the issue's author.
+    detail: String,
+    author: String,
+    pulls: Vec<LinkedPull>,
+    labels: Vec<String>,
+    /// User-facing text from the release notes section of the issue or a linked pull request,
+    /// shown in place of the title.
+    release_notes: Option<String>,
+    /// Answers to the issue form the issue was raised with.
+    fields: Answers,
+    /// The modules the ticket is counted against in the statistics.
+    modules: Vec<String>,
+    /// Whether the ticket is counted as a feature, rather than a bug fix, in the statistics.
+    feature: bool,
+    /// The full hash of the commit the ticket was read from, for `--git` reports.
+    #[serde(default)]
+    commit: Option<String>,
+    /// Whether the ticket, or a pull request which closed it, is marked as a breaking change.
+    #[serde(default)]
+    breaking: bool,
+    /// How integrators adapt to the breaking change.
+    #[serde(default)]
+    migration_notes: Option<String>,
+}
+
+impl Ticket {
+    /// The ticket's number, or the abbreviated hash of a commit which was not merged from a pull
+    /// request.
+    fn id(&self) -> String {
+        match (&self.commit, self.number) {
+            (Some(hash), 0) => short_hash(hash),
+            _ => self.number.to_string(),
+        }
+    }
+
+    /// How the ticket is referred to in announcements, such as `#123`.
+    fn reference(&self) -> String {
+        match (&self.commit, self.number) {
+            (Some(hash), 0) => short_hash(hash),
+            _ => format!("#{}", self.number),
+        }
+    }
+
+    fn row(&self, output_type: OutputType, columns: TicketColumns) -> String {
+        let mut cells = vec![self.id(), self.description(output_type)];
+        if columns.detail.is_some() {
+            cells.push(for_output(&self.detail, output_type));
+        }
+        if columns.pulls {
+            cells.push(self.pulls(output_type));
+        }
+        for field in columns.fields.keys() {
+            cells.push(
+                self.fields
+                    .get(field)
+                    .map(|answer| for_output(&escape(&answer.text()), output_type))
+                    .unwrap_or_default(),
+            );
+        }
+
+        table_row(&cells, output_type)
+    }
+
+    /// The release notes if there are any, otherwise the title.
+    fn description(&self, output_type: OutputType) -> String {
+        match &self.release_notes {
+            Some(notes) => multiline(&for_output(notes, output_type), output_type),
+            None => for_output(&self.title, output_type),
+        }
+    }
+
+    /// The description on one line and unescaped, for announcements outside the reports.
+    fn plain_description(&self) -> String {
+        let description = match &self.release_notes {
+            Some(notes) => one_line(notes),
+            None => self.title.clone(),
+        };
+        unescape(&description)
+    }
+
+    /// The migration notes on one line and unescaped, like `plain_description`.
+    fn plain_migration_notes(&self) -> Option<String> {
+        self.migration_notes
+            .as_deref()
+            .map(|notes| unescape(&one_line(notes)))
+    }
+
+    fn pulls(&self, output_type: OutputType) -> String {
+        if self.pulls.is_empty() {
+            return "N/A".to_string();
+        }
+
+        let separator = match output_type {
+            OutputType::Latex => " \\newline ",
+            OutputType::Markdown => "<br>",
+        };
+
+        self.pulls
+            .iter()
+            .map(|pr| match output_type {
+                OutputType::Latex => format!(
+                    "\\href{{{}}}{{\\#{}}} ({}, {})",
+                    pr.url,
+                    pr.number,
+                    escape(&pr.author),
+                    pr.merged_at.format("%Y-%m-%d")
+                ),
+                OutputType::Markdown => format!(
+                    "[#{}]({}) (@{}, {})",
+                    pr.number,
+                    pr.url,
+                    pr.author,
+                    pr.merged_at.format("%Y-%m-%d")
+                ),
+            })
+            .collect::<Vec<String>>()
+            .join(separator)
+    }
+}
+
+/// The optional columns shown in a client request, feature or bug fix table.
+#[derive(Clone, Copy)]
+struct TicketColumns {
+    /// The heading of the client names or author column, if it is shown.
+    detail: Option<&'static str>,
+    pulls: bool,
+    /// Issue form fields shown after the other columns, with their headings.
+    fields: &'static BTreeMap<String, String>,
+}
+
+impl TicketColumns {
+    fn new(edition: &Edition, detail: Column, heading: &'static str) -> Self {
+        TicketColumns {
+            detail: edition.has_column(detail).then_some(heading),
+            pulls: edition.has_column(Column::PullRequests),
+            fields: &config().issue_forms.columns,
+        }
+    }
+
+    fn headings(&self) -> Vec<&'static str> {
+        let mut headings = vec!["Ticket ID", "Ticket Name"];
+        headings.extend(self.detail);
+        if self.pulls {
+            headings.push("Pull Requests");
+        }
+        headings.extend(self.fields.values().map(String::as_str));
+        headings
+    }
+
+    fn header(&self, output_type: OutputType) -> String {
+        let headings = self.headings();
+        match output_type {
+            OutputType::Latex => headings
+                .iter()
+                .map(|heading| format!("\\textbf{{{}}}", heading))
+                .collect::<Vec<String>>()
+                .join(" & "),
+            OutputType::Markdown => format!(
+                "| {} |\n|{}",
+                headings
+                    .iter()
+                    .map(|heading| format!("**{}**", heading))
+                    .collect::<Vec<String>>()
+                    .join(" | "),
+                " --- |".repeat(headings.len())
+            ),
+        }
+    }
+
+    fn column_spec(&self) -> String {
+        let mut spec = "|c|p{8cm}|".to_string();
+        if self.detail.is_some() {
+            spec.push_str("p{3.5cm}|");
+        }
+        if self.pulls {
+            spec.push_str("p{4cm}|");
+        }
+        for _ in self.fields.keys() {
+            spec.push_str("p{3cm}|");
+        }
+        spec
+    }
+
+    /// A row standing in for an empty table.
+    fn placeholder(&self, message: &str, output_type: OutputType) -> String {
+        let mut cells = vec![message.to_string()];
+        cells.resize(self.headings().len(), "N/A".to_string());
+        table_row(&cells, output_type)
+    }
+}
+
+/// Text on separate lines within a table cell.
+fn multiline(text: &str, output_type: OutputType) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join(match output_type {
+            OutputType::Latex => " \\newline ",
+            OutputType::Markdown => "<br>",
+        })
+}
+
+fn one_line(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn table_row(cells: &[String], output_type: OutputType) -> String {
+    match output_type {
+        OutputType::Latex => cells.join(" & "),
+        OutputType::Markdown => format!("| {} |", cells.join(" | ")),
+    }
+}
+
+/// An issue or pull request still open in the milestone at release time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct OpenItem {
+    number: u64,
+    title: String,
+    author: String,
+    assignees: Vec<String>,
+    pull_request: bool,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct IssueData {
-    client_requests: Vec<(u64, String, String)>,
-    features: Vec<(u64, String, String)>,
-    bugfixes: Vec<(u64, String, String)>,
+    client_requests: Vec<Ticket>,
+    features: Vec<Ticket>,
+    bugfixes: Vec<Ticket>,
+    /// Issues closed without shipping, with the reason in place of the author.
+    rejected: Vec<Ticket>,
+    carried_over: Vec<OpenItem>,
     average_lifetime: i64,
     module_stats: HashMap<String, ModuleStat>,
+    /// How long each shipped bug fix carrying the regression label was open, in seconds.
+    regressions: Vec<i64>,
+    /// The manual overrides applied to the tickets above.
+    overrides: Vec<TicketOverride>,
+    /// When the release was made: when its milestone closed, its last tag or commit was made, or
+    /// its date range ended. None while the milestone is still open.
+    #[serde(default)]
+    released_at: Option<DateTime<Utc>>,
 }
 
 impl AddAssign for IssueData {
@@ -145,6 +656,8 @@ impl Add<IssueData> for IssueData {
             client_requests: [self.client_requests, other.client_requests].concat(),
             features: [self.features, other.features].concat(),
             bugfixes: [self.bugfixes, other.bugfixes].concat(),
+            rejected: [self.rejected, other.rejected].concat(),
+            carried_over: [self.carried_over, other.carried_over].concat(),
             average_lifetime: (self.average_lifetime + other.average_lifetime) / 2,
             module_stats: {
                 for (module, stat) in other.module_stats {
@@ -155,88 +668,282 @@ impl Add<IssueData> for IssueData {
                 }
                 self.module_stats
             },
+            regressions: [self.regressions, other.regressions].concat(),
+            overrides: [self.overrides, other.overrides].concat(),
+            released_at: self.released_at.max(other.released_at),
         }
     }
 }
 
 impl IssueData {
-    fn client_requests(&self, output_type: OutputType) -> String {
-        if self.client_requests.is_empty() {
-            return match output_type {
-                OutputType::Latex => "No client requests reported. & N/A & N/A".to_string(),
-                OutputType::Markdown => "| No client requests reported. | N/A | N/A |".to_string(),
-            };
+    /// The shipped tickets marked as breaking changes.
+    fn breaking_changes(&self) -> Vec<&Ticket> {
+        self.client_requests
+            .iter()
+            .chain(&self.features)
+            .chain(&self.bugfixes)
+            .filter(|ticket| ticket.breaking)
+            .collect()
+    }
+
+    fn breaking_section(&self, output_type: OutputType) -> String {
+        let breaking = self.breaking_changes();
+        if breaking.is_empty() {
+            return String::new();
         }
 
+        let rows = breaking
+            .iter()
+            .map(|ticket| {
+                let notes = ticket
+                    .migration_notes
+                    .as_deref()
+                    .map(|notes| multiline(&for_output(notes, output_type), output_type))
+                    .unwrap_or_else(|| "None given.".to_string());
+                table_row(
+                    &[ticket.id(), ticket.description(output_type), notes],
+                    output_type,
+                )
+            })
+            .collect::<Vec<String>>();
+
         match output_type {
-            OutputType::Latex => self
-                .client_requests
-                .iter()
-                .map(|cr| format!("{} & {} & {}", cr.0, cr.1, cr.2))
-                .collect::<Vec<String>>()
-                .join(" \\\\\n"),
-            OutputType::Markdown => self
-                .client_requests
-                .iter()
-                .map(|cr| format!("| {} | {} | {} |", cr.0, cr.1, cr.2))
-                .collect::<Vec<String>>()
-                .join("\n"),
+            OutputType::Latex => format!(
+                include_str!("../resources/breaking_section.tex.tmpl"),
+                breaking_table = rows.join(" \\\\\n")
+            ),
+            OutputType::Markdown => format!(
+                include_str!("../resources/breaking_section.md.tmpl"),
+                breaking_table = rows.join("\n")
+            ),
         }
     }
 
-    fn features(&self, output_type: OutputType) -> String {
-        if self.features.is_empty() {
-            return match output_type {
-                OutputType::Latex => "No features reported. & N/A & N/A".to_string(),
-                OutputType::Markdown => "| No features reported. | N/A | N/A |".to_string(),
-            };
+    fn client_request_section(&self, output_type: OutputType, edition: &Edition) -> String {
+        let columns = TicketColumns::new(edition, Column::Clients, "Client Names");
+        let rows = ticket_rows(
+            &self.client_requests,
+            "No client requests reported.",
+            columns,
+            output_type,
+        );
+
+        match output_type {
+            OutputType::Latex => format!(
+                include_str!("../resources/client_requests_section.tex.tmpl"),
+                column_spec = columns.column_spec(),
+                header = columns.header(output_type),
+                rows = rows
+            ),
+            OutputType::Markdown => format!(
+                include_str!("../resources/client_requests_section.md.tmpl"),
+                header = columns.header(output_type),
+                rows = rows
+            ),
         }
+    }
+
+    fn feature_section(&self, output_type: OutputType, edition: &Edition) -> String {
+        let columns = TicketColumns::new(edition, Column::RaisedBy, "Raised By");
+        let rows = ticket_rows(
+            &self.features,
+            "No features reported.",
+            columns,
+            output_type,
+        );
 
         match output_type {
-            OutputType::Latex => self
-                .features
-                .iter()
-                .map(|f| format!("{} & {} & {}", f.0, f.1, f.2))
-                .collect::<Vec<String>>()
-                .join(" \\\\\n"),
-            OutputType::Markdown => self
-                .features
-                .iter()
-                .map(|f| format!("| {} | {} | {} |", f.0, f.1, f.2))
-                .collect::<Vec<String>>()
-                .join("\n"),
+            OutputType::Latex => format!(
+                include_str!("../resources/features_section.tex.tmpl"),
+                column_spec = columns.column_spec(),
+                header = columns.header(output_type),
+                rows = rows
+            ),
+            OutputType::Markdown => format!(
+                include_str!("../resources/features_section.md.tmpl"),
+                header = columns.header(output_type),
+                rows = rows
+            ),
         }
     }
 
-    fn bugfixes(&self, output_type: OutputType) -> String {
-        if self.bugfixes.is_empty() {
-            return match output_type {
-                OutputType::Latex => "No bug fixes reported. & N/A & N/A".to_string(),
-                OutputType::Markdown => "| No bug fixes reported. | N/A | N/A |".to_string(),
-            };
+    fn bugfix_section(&self, output_type: OutputType, edition: &Edition) -> String {
+        let columns = TicketColumns::new(edition, Column::RaisedBy, "Raised By");
+        let rows = ticket_rows(
+            &self.bugfixes,
+            "No bug fixes reported.",
+            columns,
+            output_type,
+        );
+
+        match output_type {
+            OutputType::Latex => format!(
+                include_str!("../resources/bugfixes_section.tex.tmpl"),
+                column_spec = columns.column_spec(),
+                header = columns.header(output_type),
+                rows = rows
+            ),
+            OutputType::Markdown => format!(
+                include_str!("../resources/bugfixes_section.md.tmpl"),
+                header = columns.header(output_type),
+                rows = rows
+            ),
+        }
+    }
+
+    fn rejected_section(&self, output_type: OutputType) -> String {
+        if !config().exclusions.appendix || self.rejected.is_empty() {
+            return String::new();
         }
 
+        let rows = self
+            .rejected
+            .iter()
+            .map(|r| {
+                let cells = [
+                    r.number.to_string(),
+                    for_output(&r.title, output_type),
+                    for_output(&r.detail, output_type),
+                ];
+                table_row(&cells, output_type)
+            })
+            .collect::<Vec<String>>();
+
         match output_type {
-            OutputType::Latex => self
-                .bugfixes
-                .iter()
-                .map(|b| format!("{} & {} & {}", b.0, b.1, b.2))
-                .collect::<Vec<String>>()
-                .join(" \\\\\n"),
-            OutputType::Markdown => self
-                .bugfixes
-                .iter()
-                .map(|b| format!("| {} | {} | {} |", b.0, b.1, b.2))
-                .collect::<Vec<String>>()
-                .join("\n"),
+            OutputType::Latex => format!(
+                include_str!("../resources/rejected_section.tex.tmpl"),
+                rejected_table = rows.join(" \\\\\n")
+            ),
+            OutputType::Markdown => format!(
+                include_str!("../resources/rejected_section.md.tmpl"),
+                rejected_table = rows.join("\n")
+            ),
+        }
+    }
+
+    fn carried_over_section(&self, output_type: OutputType) -> String {
+        let rows = self
+            .carried_over
+            .iter()
+            .filter(|item| !item.pull_request)
+            .map(|i| {
+                let cells = [
+                    i.number.to_string(),
+                    for_output(&i.title, output_type),
+                    for_output(&i.author, output_type),
+                ];
+                table_row(&cells, output_type)
+            })
+            .collect::<Vec<String>>();
+
+        let table = if rows.is_empty() {
+            match output_type {
+                OutputType::Latex => "Everything planned was completed. & N/A & N/A".to_string(),
+                OutputType::Markdown => {
+                    "| Everything planned was completed. | N/A | N/A |".to_string()
+                }
+            }
+        } else {
+            rows.join(match output_type {
+                OutputType::Latex => " \\\\\n",
+                OutputType::Markdown => "\n",
+            })
+        };
+
+        match output_type {
+            OutputType::Latex => format!(
+                include_str!("../resources/carried_over_section.tex.tmpl"),
+                carried_over_table = table
+            ),
+            OutputType::Markdown => format!(
+                include_str!("../resources/carried_over_section.md.tmpl"),
+                carried_over_table = table
+            ),
+        }
+    }
+
+    fn carried_over_appendix(&self, output_type: OutputType) -> String {
+        if !config().carry_over.appendix || self.carried_over.is_empty() {
+            return String::new();
+        }
+
+        let rows = self
+            .carried_over
+            .iter()
+            .map(|i| {
+                let kind = if i.pull_request {
+                    "Pull request"
+                } else {
+                    "Issue"
+                };
+                let assignees = if i.assignees.is_empty() {
+                    "Unassigned".to_string()
+                } else {
+                    i.assignees.join(", ")
+                };
+
+                let cells = [
+                    i.number.to_string(),
+                    for_output(&i.title, output_type),
+                    kind.to_string(),
+                    for_output(&assignees, output_type),
+                ];
+                table_row(&cells, output_type)
+            })
+            .collect::<Vec<String>>();
+
+        match output_type {
+            OutputType::Latex => format!(
+                include_str!("../resources/carried_over_appendix.tex.tmpl"),
+                open_item_table = rows.join(" \\\\\n")
+            ),
+            OutputType::Markdown => format!(
+                include_str!("../resources/carried_over_appendix.md.tmpl"),
+                open_item_table = rows.join("\n")
+            ),
+        }
+    }
+
+    /// Adds (or with `count` false, removes) a ticket's contribution to the module statistics.
+    fn count_modules(&mut self, ticket: &Ticket, count: bool) {
+        for module in &ticket.modules {
+            let stat = self.module_stats.entry(module.clone()).or_default();
+            let total = if ticket.feature {
+                &mut stat.features
+            } else {
+                &mut stat.bugs
+            };
+
+            if count {
+                *total += 1;
+            } else {
+                *total = total.saturating_sub(1);
+            }
         }
     }
 
+    /// Rebuilds the module statistics from the shipped tickets, after some were left out.
+    fn recount_modules(&mut self) {
+        let mut recounted = IssueData::default();
+        for ticket in self
+            .client_requests
+            .iter()
+            .chain(&self.features)
+            .chain(&self.bugfixes)
+        {
+            recounted.count_modules(ticket, true);
+        }
+        self.module_stats = recounted.module_stats;
+    }
+
     fn module_stats(&self, output_type: OutputType) -> String {
         let mut stat_data: Vec<(String, usize, usize)> = self
             .module_stats
             .iter()
-            .map(|(module, count)| (module.clone(), count.features, count.bugs))
+            .map(|(module, count)| {
+                let name = for_output(&escape(module), output_type);
+                (name, count.features, count.bugs)
+            })
             .collect();
         stat_data.sort_by(|a, b| a.0.cmp(&b.0));
 
@@ -284,48 +991,114 @@ impl IssueData {
     }
 }
 
-async fn fetch_issues(version: &str, repo: &str) -> Result<IssueData> {
-    let mut issue_aggregator = Vec::new();
+fn ticket_rows(
+    tickets: &[Ticket],
+    empty: &str,
+    columns: TicketColumns,
+    output_type: OutputType,
+) -> String {
+    if tickets.is_empty() {
+        return columns.placeholder(empty, output_type);
+    }
 
-    let issues = client()
-        .search()
-        .issues_and_pull_requests(&format!(
-            "milestone:{} repo:marketdojo/{} is:closed is:issue",
-            version, repo
-        ))
-        .per_page(100)
-        .send()
-        .await?
-        .into_stream(client());
+    tickets
+        .iter()
+        .map(|ticket| ticket.row(output_type, columns))
+        .collect::<Vec<String>>()
+        .join(match output_type {
+            OutputType::Latex => " \\\\\n",
+            OutputType::Markdown => "\n",
+        })
+}
 
-    pin!(issues);
+/// The issues of `repo` within the selection, or nothing if it has no shipped issues there.
+async fn fetch_issues(selection: &Selection, repo: &str) -> Result<Option<IssueData>> {
+    let mut issue_aggregator = Vec::new();
 
     let mut client_requests = Vec::new();
     let mut features = Vec::new();
     let mut bugfixes = Vec::new();
+    let mut rejected = Vec::new();
     let mut module_stats = HashMap::new();
+    let mut regressions = Vec::new();
+    let infer_modules = inference_enabled().await?;
+    let forms = FormParser::load(repo).await?;
+    let mut changed_files = HashMap::new();
+
+    for closed in selection.closed_issues(repo).await? {
+        let reason = rejection_reason(&closed);
+        let issue = closed.issue;
+        let title = title(&issue);
+
+        if let Some(reason) = reason {
+            rejected.push(Ticket {
+                number: issue.number,
+                title,
+                detail: reason,
+                author: escape(&issue.user.login),
+                pulls: Vec::new(),
+                labels: labels(&issue),
+                release_notes: None,
+                fields: Answers::new(),
+                modules: Vec::new(),
+                feature: false,
+                commit: None,
+                breaking: false,
+                migration_notes: None,
+            });
+            continue;
+        }
+
+        let regression = issue
+            .closed_at
+            .filter(|_| is_regression(&issue))
+            .map(|closed_at| (closed_at - issue.created_at).num_seconds());
 
-    while let Some(issue) = issues.try_next().await? {
         issue_aggregator.push(issue.clone());
-        let title = title(&issue);
         let body = body(&issue);
-        let client_details = client_details(&body).await;
-        let modules = module_details(&body).await;
+        let answers = forms.answers(&body);
+        let client_details = match issue_forms::clients(&answers) {
+            Some(clients) => Some(clients),
+            None => client_details(&body).await,
+        };
+        let pulls = linked_pulls(repo, issue.number).await?;
+        let inferred = if infer_modules {
+            inferred_modules(repo, &pulls, &mut changed_files).await?
+        } else {
+            Vec::new()
+        };
+        let declared = match issue_forms::modules(&answers) {
+            Some(modules) => Some(modules),
+            None => module_details(&body)
+                .await
+                .map(|declared| declared.into_iter().map(String::from).collect()),
+        };
+        let modules = declared
+            .map(|declared| resolve_modules(declared, inferred))
+            .unwrap_or_default();
         let feature = feature_regexp().await?.is_match(&title);
+        let breaking = marked_breaking(&labels(&issue), &issue.title)
+            || issue_forms::breaking(&answers)
+            || pulls.iter().any(|pull| pull.breaking);
+        let migration_notes = match issue_forms::migration_notes(&answers) {
+            Some(notes) => Some(escape(&notes)),
+            None => match migration_notes(&body).await {
+                Some(notes) => Some(escape(&notes)),
+                None => pulls.iter().find_map(|pull| pull.migration_notes.clone()),
+            },
+        };
 
-        if let Some(modules) = modules {
+        if !modules.is_empty() {
             // In debug env, print the modules for each issue
             if cfg!(debug_assertions) {
                 println!("{}: {}", title, modules.join(", "));
             }
 
-            for module in modules {
-                let stat = module_stats
-                    .entry(module.to_string())
-                    .or_insert(ModuleStat {
-                        bugs: 0,
-                        features: 0,
-                    });
+            for module in modules.iter().cloned() {
+                let stat = module_stats.entry(module).or_insert(ModuleStat {
+                    bugs: 0,
+                    features: 0,
+                });
 
                 if feature {
                     stat.features += 1;
@@ -335,27 +1108,185 @@ async fn fetch_issues(version: &str, repo: &str) -> Result<IssueData> {
             }
         }
 
-        if let Some(details) = client_details {
-            client_requests.push((issue.number, title, details));
+        let client_requested = client_details.is_some();
+        let ticket = Ticket {
+            number: issue.number,
+            title,
+            detail: client_details.unwrap_or_else(|| escape(&issue.user.login)),
+            author: escape(&issue.user.login),
+            release_notes: match issue_forms::release_notes(&answers) {
+                Some(notes) => Some(escape(&notes)),
+                None => match release_notes(&body).await {
+                    Some(notes) => Some(escape(&notes)),
+                    None => pulls.iter().find_map(|pull| pull.release_notes.clone()),
+                },
+            },
+            pulls,
+            labels: labels(&issue),
+            fields: answers,
+            modules,
+            feature,
+            commit: None,
+            breaking,
+            migration_notes,
+        };
+
+        if client_requested {
+            client_requests.push(ticket);
         } else if feature {
-            features.push((issue.number, title, issue.user.login));
+            features.push(ticket);
         } else {
-            bugfixes.push((issue.number, title, issue.user.login));
+            bugfixes.push(ticket);
+            regressions.extend(regression);
         }
     }
 
-    let average_lifetime = average_lifetime(issue_aggregator.into_iter())?;
+    let Some(average_lifetime) = average_lifetime(issue_aggregator.into_iter()) else {
+        return Ok(None);
+    };
+    let carried_over = fetch_open_items(selection, repo).await?;
 
-    Ok(IssueData {
+    Ok(Some(IssueData {
         client_requests,
         features,
         bugfixes,
+        rejected,
+        carried_over,
         average_lifetime,
         module_stats,
-    })
+        regressions,
+        overrides: Vec::new(),
+        released_at: None,
+    }))
+}
+
+/// Issues and pull requests which are still open in the milestone, and so did not ship with it.
+async fn fetch_open_items(selection: &Selection, repo: &str) -> Result<Vec<OpenItem>> {
+    Ok(selection
+        .open_items(repo)
+        .await?
+        .into_iter()
+        .map(|item| OpenItem {
+            number: item.number,
+            title: title(&item),
+            author: escape(&item.user.login),
+            assignees: item.assignees.iter().map(|a| escape(&a.login)).collect(),
+            pull_request: item.pull_request.is_some(),
+            labels: labels(&item),
+        })
+        .collect())
+}
+
+/// Why a closed issue does not count as shipped: closed as anything but completed, such as
+/// not planned or a duplicate, or carrying one of the configured exclusion labels.
+fn rejection_reason(closed: &ClosedIssue) -> Option<String> {
+    if let Some(reason) = closed.unshipped_reason() {
+        return Some(format!("Closed as {}", reason.replace('_', " ")));
+    }
+
+    let excluded = &config().exclusions.labels;
+    closed
+        .issue
+        .labels
+        .iter()
+        .find(|label| excluded.iter().any(|e| e.eq_ignore_ascii_case(&label.name)))
+        .map(|label| format!("Labelled {}", escape(&label.name)))
 }
 
-fn average_lifetime(issues: IntoIter<octocrab::models::issues::Issue>) -> Result<i64> {
+fn labels(issue: &Issue) -> Vec<String> {
+    issue
+        .labels
+        .iter()
+        .map(|label| label.name.clone())
+        .collect()
+}
+
+/// Whether the issue fixed a problem introduced by an earlier release.
+fn is_regression(issue: &Issue) -> bool {
+    let label = &config().delivery.regression_label;
+    issue
+        .labels
+        .iter()
+        .any(|l| l.name.eq_ignore_ascii_case(label))
+}
+
+/// Whether a label or a marker in the title flags an issue or pull request as a breaking change.
+fn marked_breaking(labels: &[String], title: &str) -> bool {
+    let settings = &config().breaking;
+    let title = title.to_lowercase();
+    labels.iter().any(|label| {
+        settings
+            .labels
+            .iter()
+            .any(|l| l.eq_ignore_ascii_case(label))
+    }) || settings
+        .title_markers
+        .iter()
+        .any(|marker| title.contains(&marker.to_lowercase()))
+}
+
+/// An issue with the reason GitHub gave for closing it. octocrab's `IssueStateReason` has no
+/// variant for newer reasons such as `duplicate`, which would fail the whole response, so the
+/// reason is taken out as text before the rest is deserialised.
+struct ClosedIssue {
+    issue: Issue,
+    state_reason: Option<String>,
+}
+
+impl ClosedIssue {
+    fn from_value(mut value: serde_json::Value) -> Result<Self> {
+        let state_reason = value
+            .get_mut("state_reason")
+            .map(serde_json::Value::take)
+            .and_then(|reason| reason.as_str().map(String::from));
+
+        Ok(ClosedIssue {
+            issue: serde_json::from_value(value)?,
+            state_reason,
+        })
+    }
+
+    /// How the issue was closed, unless it was completed. Issues closed before GitHub recorded
+    /// reasons have none, and count as completed.
+    fn unshipped_reason(&self) -> Option<&str> {
+        self.state_reason
+            .as_deref()
+            .filter(|reason| *reason != "completed")
+    }
+}
+
+/// Collects every issue or pull request matching a search query.
+async fn search(query: &str) -> Result<Vec<Issue>> {
+    Ok(search_closed(query)
+        .await?
+        .into_iter()
+        .map(|closed| closed.issue)
+        .collect())
+}
+
+/// Like `search`, keeping the reason each issue was closed.
+async fn search_closed(query: &str) -> Result<Vec<ClosedIssue>> {
+    let page: Page<serde_json::Value> = client()
+        .get("/search/issues", Some(&[("q", query), ("per_page", "100")]))
+        .await?;
+
+    client()
+        .all_pages(page)
+        .await?
+        .into_iter()
+        .map(ClosedIssue::from_value)
+        .collect()
+}
+
+/// One issue or pull request, keeping the reason it was closed.
+async fn get_issue(repo: &str, number: u64) -> Result<ClosedIssue> {
+    let value = client()
+        .get(format!("/repos/{}/issues/{}", repo, number), None::<&()>)
+        .await?;
+    ClosedIssue::from_value(value)
+}
+
+fn average_lifetime(issues: IntoIter<octocrab::models::issues::Issue>) -> Option<i64> {
     let len = issues.len() as i64;
 
     issues
@@ -368,16 +1299,76 @@ fn average_lifetime(issues: IntoIter<octocrab::models::issues::Issue>) -> Result
         })
         .sum::<i64>()
         .checked_div(len)
-        .ok_or(eyre!("No issues found for this milestone"))
 }
 
 fn title(issue: &Issue) -> String {
-    issue
-        .title
-        .trim()
-        .replace('_', "\\_")
-        .replace('&', "\\&")
-        .replace('#', "\\#")
+    escape(issue.title.trim())
+}
+
+/// LaTeX commands for the special characters which cannot simply be preceded by a backslash.
+const LATEX_COMMANDS: [(char, &str); 3] = [
+    ('\\', "\\textbackslash{}"),
+    ('^', "\\textasciicircum{}"),
+    ('~', "\\textasciitilde{}"),
+];
+
+/// Characters LaTeX shows literally when preceded by a backslash.
+const LATEX_ESCAPED: [char; 7] = ['_', '&', '#', '%', '$', '{', '}'];
+
+/// Escapes the characters LaTeX treats specially. Text is kept in this form, and converted with
+/// `for_output` for Markdown or `unescape` for anything else.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if let Some((_, command)) = LATEX_COMMANDS.iter().find(|(special, _)| *special == c) {
+            escaped.push_str(command);
+        } else {
+            if LATEX_ESCAPED.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape`, for output which is neither LaTeX nor Markdown.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('\\') {
+        unescaped.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        if let Some((special, command)) = LATEX_COMMANDS
+            .iter()
+            .find(|(_, command)| rest.starts_with(command))
+        {
+            unescaped.push(*special);
+            rest = &rest[command.len()..];
+        } else if let Some(c) = rest[1..]
+            .chars()
+            .next()
+            .filter(|c| LATEX_ESCAPED.contains(c))
+        {
+            unescaped.push(c);
+            rest = &rest[1 + c.len_utf8()..];
+        } else {
+            unescaped.push('\\');
+            rest = &rest[1..];
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Escaped text as a report shows it: unchanged in LaTeX, and in Markdown unescaped with the
+/// table cell separator escaped instead.
+fn for_output(text: &str, output_type: OutputType) -> String {
+    match output_type {
+        OutputType::Latex => text.to_string(),
+        OutputType::Markdown => unescape(text).replace('|', "\\|"),
+    }
 }
 
 fn body(issue: &Issue) -> String {
@@ -388,21 +1379,33 @@ fn body(issue: &Issue) -> String {
         .unwrap_or_default()
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct PrStats {
     total_count: usize,
     average_lifetime: i64,
     contributor_count: usize,
+    contributors: BTreeMap<String, Contribution>,
+    cycle_times: CycleTimes,
 }
 
 impl Add<PrStats> for PrStats {
     type Output = PrStats;
 
-    fn add(self, other: PrStats) -> PrStats {
+    fn add(mut self, other: PrStats) -> PrStats {
         PrStats {
             total_count: self.total_count + other.total_count,
             average_lifetime: (self.average_lifetime + other.average_lifetime) / 2,
             contributor_count: self.contributor_count + other.contributor_count,
+            contributors: {
+                for (author, contribution) in other.contributors {
+                    *self.contributors.entry(author).or_default() += contribution;
+                }
+                self.contributors
+            },
+            cycle_times: {
+                self.cycle_times += other.cycle_times;
+                self.cycle_times
+            },
         }
     }
 }
@@ -413,16 +1416,24 @@ impl AddAssign for PrStats {
     }
 }
 
-async fn pr_stats(version: &str, repo: &str) -> Result<PrStats> {
-    let pulls = client()
-        .search()
-        .issues_and_pull_requests(&format!(
-            "milestone:{} repo:marketdojo/{} is:closed is:pr",
-            version, repo
-        ))
-        .send()
-        .await?
-        .into_iter();
+/// The pull requests of `repo` within the selection, or nothing if it has none there.
+async fn pr_stats(
+    selection: &Selection,
+    repo: &str,
+    released_at: DateTime<Utc>,
+) -> Result<Option<PrStats>> {
+    let closed_pulls = selection.merged_pulls(repo).await?;
+    if closed_pulls.is_empty() {
+        return Ok(None);
+    }
+
+    let mut reviews = HashMap::new();
+    for pull in &closed_pulls {
+        reviews.insert(pull.number, links::reviews(repo, pull.number).await?);
+    }
+    let contributors = contributions(repo, &closed_pulls, &reviews).await?;
+    let cycle_times = cycle_times(repo, &closed_pulls, &reviews, released_at).await?;
+    let pulls = closed_pulls.into_iter();
     let len = pulls.len();
 
     let mut stats = PrStats {
@@ -433,73 +1444,175 @@ async fn pr_stats(version: &str, repo: &str) -> Result<PrStats> {
             .map(|pr| pr.user.login)
             .collect::<std::collections::HashSet<_>>()
             .len(),
+        contributors,
+        cycle_times,
     };
 
-    let average_lifetime = pulls
+    stats.average_lifetime = pulls
         .filter_map(|pr| {
             pr.closed_at
                 .and_then(|closed_at| closed_at.timestamp().checked_sub(pr.created_at.timestamp()))
         })
         .sum::<i64>()
-        .checked_div(len as i64)
-        .ok_or(eyre!("No PRs found for this milestone"))?;
+        / len as i64;
 
-    stats.average_lifetime = average_lifetime;
-
-    Ok(stats)
+    Ok(Some(stats))
 }
 
 async fn construct_latex_report(
-    versions: &[String],
+    selections: &[Selection],
     issues: &IssueData,
     pull_stats: &PrStats,
+    previous: Option<&ReleaseSummary>,
+    delivery: Option<&DeliveryMetrics>,
+    edition: &Edition,
 ) -> String {
+    let output_type = OutputType::Latex;
+
     std::fmt::format(format_args!(
         include_str!("../resources/report_format.tex.tmpl"),
-        versions = versions
-            .iter()
-            .map(|v| format!("v{}", v))
-            .collect::<Vec<String>>()
-            .join(", "),
-        n_prs = pull_stats.total_count,
-        n_closed = issues.client_requests.len() + issues.features.len() + issues.bugfixes.len(),
-        client_request_table = issues.client_requests(OutputType::Latex),
-        feature_table = issues.features(OutputType::Latex),
-        bugfix_table = issues.bugfixes(OutputType::Latex),
-        avg_lifetime = duration_to_string(chrono::Duration::seconds(issues.average_lifetime)),
-        avg_pr_lifetime =
-            duration_to_string(chrono::Duration::seconds(pull_stats.average_lifetime)),
-        module_table = issues.module_stats(OutputType::Latex),
-        n_contributors = pull_stats.contributor_count,
+        versions = release_name(selections),
+        breaking_section = edition.render(Section::Breaking, || {
+            issues.breaking_section(output_type)
+        }),
+        client_request_section = edition.render(Section::ClientRequests, || {
+            issues.client_request_section(output_type, edition)
+        }),
+        feature_section = edition.render(Section::Features, || {
+            issues.feature_section(output_type, edition)
+        }),
+        bugfix_section = edition.render(Section::Bugfixes, || {
+            issues.bugfix_section(output_type, edition)
+        }),
+        carried_over_section = edition.render(Section::CarriedOver, || {
+            if config().carry_over.section && selections.iter().any(Selection::is_milestone) {
+                issues.carried_over_section(output_type)
+            } else {
+                String::new()
+            }
+        }),
+        statistics_section = edition.render(Section::Statistics, || {
+            statistics_section(selections, issues, pull_stats, previous, output_type)
+        }),
+        delivery_section = edition.render(Section::Delivery, || {
+            delivery
+                .map(|delivery| delivery.section(output_type))
+                .unwrap_or_default()
+        }),
+        contributors_section = edition.render(Section::Contributors, || {
+            contributors_section(issues, pull_stats, output_type, edition)
+        }),
+        rejected_section = edition.render(Section::Rejected, || {
+            issues.rejected_section(output_type)
+        }),
+        carried_over_appendix = edition.render(Section::CarriedOverAppendix, || {
+            issues.carried_over_appendix(output_type)
+        }),
     ))
 }
 
 async fn construct_markdown_report(
-    versions: &[String],
+    selections: &[Selection],
     issues: &IssueData,
     pull_stats: &PrStats,
+    previous: Option<&ReleaseSummary>,
+    delivery: Option<&DeliveryMetrics>,
+    edition: &Edition,
 ) -> String {
+    let output_type = OutputType::Markdown;
+
     std::fmt::format(format_args!(
         include_str!("../resources/report_format.md.tmpl"),
         release_date = chrono::Utc::now().format("%Y-%m-%d"),
-        versions = versions
-            .iter()
-            .map(|v| format!("v{}", v))
-            .collect::<Vec<String>>()
-            .join(", "),
-        n_prs = pull_stats.total_count,
-        n_closed = issues.client_requests.len() + issues.features.len() + issues.bugfixes.len(),
-        client_request_table = issues.client_requests(OutputType::Markdown),
-        feature_table = issues.features(OutputType::Markdown),
-        bugfix_table = issues.bugfixes(OutputType::Markdown),
-        avg_lifetime = duration_to_string(chrono::Duration::seconds(issues.average_lifetime)),
-        avg_pr_lifetime =
-            duration_to_string(chrono::Duration::seconds(pull_stats.average_lifetime)),
-        module_table = issues.module_stats(OutputType::Markdown),
-        n_contributors = pull_stats.contributor_count,
+        versions = release_name(selections),
+        breaking_section = edition.render(Section::Breaking, || {
+            issues.breaking_section(output_type)
+        }),
+        client_request_section = edition.render(Section::ClientRequests, || {
+            issues.client_request_section(output_type, edition)
+        }),
+        feature_section = edition.render(Section::Features, || {
+            issues.feature_section(output_type, edition)
+        }),
+        bugfix_section = edition.render(Section::Bugfixes, || {
+            issues.bugfix_section(output_type, edition)
+        }),
+        carried_over_section = edition.render(Section::CarriedOver, || {
+            if config().carry_over.section && selections.iter().any(Selection::is_milestone) {
+                issues.carried_over_section(output_type)
+            } else {
+                String::new()
+            }
+        }),
+        statistics_section = edition.render(Section::Statistics, || {
+            statistics_section(selections, issues, pull_stats, previous, output_type)
+        }),
+        delivery_section = edition.render(Section::Delivery, || {
+            delivery
+                .map(|delivery| delivery.section(output_type))
+                .unwrap_or_default()
+        }),
+        contributors_section = edition.render(Section::Contributors, || {
+            contributors_section(issues, pull_stats, output_type, edition)
+        }),
+        rejected_section = edition.render(Section::Rejected, || {
+            issues.rejected_section(output_type)
+        }),
+        carried_over_appendix = edition.render(Section::CarriedOverAppendix, || {
+            issues.carried_over_appendix(output_type)
+        }),
     ))
 }
 
+fn statistics_section(
+    selections: &[Selection],
+    issues: &IssueData,
+    pull_stats: &PrStats,
+    previous: Option<&ReleaseSummary>,
+    output_type: OutputType,
+) -> String {
+    let n_closed = issues.client_requests.len() + issues.features.len() + issues.bugfixes.len();
+    let avg_lifetime = duration_to_string(chrono::Duration::seconds(issues.average_lifetime));
+    let avg_pr_lifetime =
+        duration_to_string(chrono::Duration::seconds(pull_stats.average_lifetime));
+    let comparison = previous
+        .map(|previous| {
+            ReleaseSummary::new(
+                file_stem(selections),
+                release_name(selections),
+                issues,
+                pull_stats,
+            )
+            .comparison(previous, output_type)
+        })
+        .unwrap_or_default();
+
+    match output_type {
+        OutputType::Latex => format!(
+            include_str!("../resources/statistics_section.tex.tmpl"),
+            n_prs = pull_stats.total_count,
+            n_contributors = pull_stats.contributor_count,
+            n_closed = n_closed,
+            avg_lifetime = avg_lifetime,
+            avg_pr_lifetime = avg_pr_lifetime,
+            comparison = comparison,
+            module_table = issues.module_stats(output_type),
+            cycle_times = pull_stats.cycle_times.table(output_type),
+        ),
+        OutputType::Markdown => format!(
+            include_str!("../resources/statistics_section.md.tmpl"),
+            n_prs = pull_stats.total_count,
+            n_contributors = pull_stats.contributor_count,
+            n_closed = n_closed,
+            avg_lifetime = avg_lifetime,
+            avg_pr_lifetime = avg_pr_lifetime,
+            comparison = comparison,
+            module_table = issues.module_stats(output_type),
+            cycle_times = pull_stats.cycle_times.table(output_type),
+        ),
+    }
+}
+
 // tectonic <input> --outfmt <format> --chatter <level> --pass <pass> --format <path> --color <when>
 async fn generate_pdf(path: &str) -> Result<()> {
     let dir_path = "resources";
@@ -527,37 +1640,196 @@ async fn generate_pdf(path: &str) -> Result<()> {
     Ok(())
 }
 
-async fn run(versions: Vec<String>) -> Result<i32> {
+async fn run(
+    selections: Vec<Selection>,
+    editions: &[String],
+    overrides: Option<PathBuf>,
+    review: bool,
+    fail_on_breaking: bool,
+    publish: bool,
+) -> Result<i32> {
+    let editions = Edition::selected(editions)?;
+
     info!("Fetching issues");
     let mut issues = IssueData::default();
     let mut pull_stats = PrStats::default();
 
-    for version in &versions {
-        issues += fetch_issues(version, "auction").await?;
-        pull_stats += pr_stats(version, "auction").await?;
+    if let [Selection::Commits {
+        repository,
+        from,
+        to,
+    }] = selections.as_slice()
+    {
+        (issues, pull_stats) = commits::read(repository, from, to).await?;
+    } else {
+        let mut released_at = None;
+        for repo in &config().repositories {
+            for selection in &selections {
+                released_at = released_at.max(selection.released_at(repo).await?);
+            }
+        }
+        let released_at = released_at.unwrap_or_else(Utc::now);
+        issues.released_at = Some(released_at);
+
+        let (mut found_issues, mut found_pulls) = (false, false);
+        for repo in &config().repositories {
+            for selection in &selections {
+                match fetch_issues(selection, repo).await? {
+                    Some(repo_issues) => {
+                        issues += repo_issues;
+                        found_issues = true;
+                    }
+                    None => warn!(
+                        "No issues found for {} in {}, so it is skipped",
+                        selection.name(),
+                        repo
+                    ),
+                }
+                match pr_stats(selection, repo, released_at).await? {
+                    Some(repo_stats) => {
+                        pull_stats += repo_stats;
+                        found_pulls = true;
+                    }
+                    None => warn!(
+                        "No PRs found for {} in {}, so it is skipped",
+                        selection.name(),
+                        repo
+                    ),
+                }
+            }
+        }
+
+        if !found_issues {
+            return Err(eyre!(
+                "No issues found for {} in any repository",
+                release_name(&selections)
+            ));
+        }
+        if !found_pulls {
+            return Err(eyre!(
+                "No PRs found for {} in any repository",
+                release_name(&selections)
+            ));
+        }
+    }
+
+    let overrides = overrides.unwrap_or_else(|| Overrides::default_path(&file_stem(&selections)));
+    let mut ticket_overrides = Overrides::load(&overrides)?;
+    if review {
+        ticket_overrides = review::review(&issues, ticket_overrides, &overrides)?;
+    }
+    ticket_overrides.apply(&mut issues);
+
+    let breaking = issues.breaking_changes();
+    if fail_on_breaking && !breaking.is_empty() {
+        for ticket in &breaking {
+            error!(
+                "Breaking change {}: {}",
+                ticket.reference(),
+                ticket.plain_description()
+            );
+        }
+        error!(
+            "{} breaking changes found, so no reports were generated",
+            breaking.len()
+        );
+        return Ok(2);
     }
 
     info!("Feature count: {}", issues.features.len());
     info!("Bug count: {}", issues.bugfixes.len());
     info!("Client request count: {}", issues.client_requests.len());
 
-    latex_report(&versions, &issues, &pull_stats).await?;
-    info!("Generated LaTeX and PDF reports");
+    let history = &config().history;
+    let summary = ReleaseSummary::new(
+        file_stem(&selections),
+        release_name(&selections),
+        &issues,
+        &pull_stats,
+    );
+    let previous = if history.compare_previous {
+        history::previous(&summary)?
+    } else {
+        None
+    };
+    let delivery = if history.enabled {
+        let delivery = DeliveryMetrics::new(summary.clone(), issues.overrides.clone()).await?;
+        delivery.export()?;
+        Some(delivery)
+    } else {
+        None
+    };
+
+    for edition in &editions {
+        let issues = edition.apply(&issues);
+        let name = edition.name.as_deref().unwrap_or("full");
+
+        latex_report(
+            &selections,
+            &issues,
+            &pull_stats,
+            previous.as_ref(),
+            delivery.as_ref(),
+            edition,
+        )
+        .await?;
+        info!("Generated {} LaTeX and PDF reports", name);
+
+        markdown_report(
+            &selections,
+            &issues,
+            &pull_stats,
+            previous.as_ref(),
+            delivery.as_ref(),
+            edition,
+        )
+        .await?;
+        info!("Generated {} Markdown report", name);
+    }
+
+    if config().feed.enabled {
+        feed::update(&summary, &issues)?;
+    }
+
+    if config().changelog.enabled {
+        changelog::update(&summary, &issues)?;
+    }
+
+    if history.enabled {
+        history::record(&summary, &issues, &pull_stats)?;
+        info!("Recorded release in {}", history.database.display());
+    }
 
-    markdown_report(&versions, &issues, &pull_stats).await?;
-    info!("Generated Markdown report");
+    if publish {
+        if slack::webhook().is_none() && config().webhooks.is_empty() {
+            warn!("No publishers are configured, so the release was not announced");
+        }
+        slack::announce(&issues, &summary.name, &summary.key).await?;
+        webhook::publish(&issues, &summary.name, &summary.key).await?;
+    }
 
     Ok(0)
 }
 
-async fn latex_report(versions: &[String], issues: &IssueData, pull_stats: &PrStats) -> Result<()> {
+async fn latex_report(
+    selections: &[Selection],
+    issues: &IssueData,
+    pull_stats: &PrStats,
+    previous: Option<&ReleaseSummary>,
+    delivery: Option<&DeliveryMetrics>,
+    edition: &Edition,
+) -> Result<()> {
     let dir_path = "releases";
     DirBuilder::new().recursive(true).create(dir_path)?;
-    let path = format!("releases/release-{}.tex", versions.join("-"));
+    let path = format!(
+        "releases/release-{}{}.tex",
+        file_stem(selections),
+        edition.file_suffix()
+    );
     let mut file = File::create(&path)?;
 
     file.write_all(
-        construct_latex_report(versions, issues, pull_stats)
+        construct_latex_report(selections, issues, pull_stats, previous, delivery, edition)
             .await
             .as_bytes(),
     )?;
@@ -566,20 +1838,155 @@ async fn latex_report(versions: &[String], issues: &IssueData, pull_stats: &PrSt
 }
 
 async fn markdown_report(
-    versions: &[String],
+    selections: &[Selection],
     issues: &IssueData,
     pull_stats: &PrStats,
+    previous: Option<&ReleaseSummary>,
+    delivery: Option<&DeliveryMetrics>,
+    edition: &Edition,
 ) -> Result<()> {
     let dir_path = "releases";
     DirBuilder::new().recursive(true).create(dir_path)?;
-    let path = format!("releases/release-{}.md", versions.join("-"));
+    let path = format!(
+        "releases/release-{}{}.md",
+        file_stem(selections),
+        edition.file_suffix()
+    );
     let mut file = File::create(&path)?;
 
     file.write_all(
-        construct_markdown_report(versions, issues, pull_stats)
+        construct_markdown_report(selections, issues, pull_stats, previous, delivery, edition)
             .await
             .as_bytes(),
     )?;
 
     Ok(())
 }
+
+fn release_name(selections: &[Selection]) -> String {
+    selections
+        .iter()
+        .map(Selection::name)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn file_stem(selections: &[Selection]) -> String {
+    selections
+        .iter()
+        .map(Selection::file_stem)
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+#[cfg(test)]
+impl Ticket {
+    /// A bug fix with no pull requests, labels or modules, for tests to build on.
+    fn fixture(number: u64, title: &str) -> Ticket {
+        Ticket {
+            number,
+            title: title.to_string(),
+            detail: "author".to_string(),
+            author: "author".to_string(),
+            pulls: Vec::new(),
+            labels: Vec::new(),
+            release_notes: None,
+            fields: Answers::new(),
+            modules: Vec::new(),
+            feature: false,
+            commit: None,
+            breaking: false,
+            migration_notes: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_item(number: u64, title: &str, pull_request: bool) -> OpenItem {
+        OpenItem {
+            number,
+            title: escape(title),
+            author: escape("jo_smith"),
+            assignees: Vec::new(),
+            pull_request,
+            labels: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn carries_over_open_issues_but_not_pull_requests() {
+        let issues = IssueData {
+            carried_over: vec![
+                open_item(12, "Export | import 100% of bids", false),
+                open_item(13, "Draft export", true),
+            ],
+            ..IssueData::default()
+        };
+
+        let markdown = issues.carried_over_section(OutputType::Markdown);
+        assert!(markdown.contains("| 12 | Export \\| import 100% of bids | jo_smith |"));
+        assert!(!markdown.contains("Draft export"));
+        assert!(issues
+            .carried_over_section(OutputType::Latex)
+            .contains(r"12 & Export | import 100\% of bids & jo\_smith"));
+    }
+
+    #[test]
+    fn says_when_nothing_was_carried_over() {
+        let issues = IssueData {
+            carried_over: vec![open_item(13, "Draft export", true)],
+            ..IssueData::default()
+        };
+
+        assert!(issues
+            .carried_over_section(OutputType::Markdown)
+            .contains("| Everything planned was completed. | N/A | N/A |"));
+        assert!(IssueData::default()
+            .carried_over_section(OutputType::Latex)
+            .contains("Everything planned was completed. & N/A & N/A"));
+    }
+
+    #[test]
+    fn escapes_every_latex_special_character() {
+        assert_eq!(
+            escape(r"50% of $5 {a_b} & #1 \ ^ ~"),
+            r"50\% of \$5 \{a\_b\} \& \#1 \textbackslash{} \textasciicircum{} \textasciitilde{}"
+        );
+    }
+
+    #[test]
+    fn unescapes_what_was_escaped() {
+        let text = r"C:\path\{x} costs 5$ ~ 10% ^ more & #2 for a|b_c";
+        assert_eq!(unescape(&escape(text)), text);
+        assert_eq!(unescape(r"a \newline b"), r"a \newline b");
+    }
+
+    #[test]
+    fn escapes_table_cells_for_markdown() {
+        let escaped = escape("a|b & c_d");
+        assert_eq!(for_output(&escaped, OutputType::Latex), r"a|b \& c\_d");
+        assert_eq!(for_output(&escaped, OutputType::Markdown), r"a\|b & c_d");
+    }
+
+    #[test]
+    fn escapes_migration_notes_for_each_output() {
+        let issues = IssueData {
+            features: vec![Ticket {
+                breaking: true,
+                migration_notes: Some(escape("Rename bid_id | lot_id\nThen 100% done")),
+                ..Ticket::fixture(7, &escape("Split_bids"))
+            }],
+            ..IssueData::default()
+        };
+
+        assert!(issues
+            .breaking_section(OutputType::Latex)
+            .contains(r"Rename bid\_id | lot\_id \newline Then 100\% done"));
+        assert!(issues
+            .breaking_section(OutputType::Markdown)
+            .contains(r"Split_bids | Rename bid_id \| lot_id<br>Then 100% done |"));
+    }
+}
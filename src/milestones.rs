@@ -0,0 +1,479 @@
+use crate::{
+    client,
+    config::{config, NamingScheme},
+    owner_and_name,
+    regex::version_regexp,
+    search,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use color_eyre::{eyre::eyre, Result};
+use octocrab::{models::Milestone, Page};
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// Every milestone in the repository, open or closed.
+pub async fn list_milestones(repo: &str) -> Result<Vec<Milestone>> {
+    let page: Page<Milestone> = client()
+        .get(
+            format!("/repos/{}/milestones", repo),
+            Some(&[("state", "all"), ("per_page", "100")]),
+        )
+        .await?;
+
+    Ok(client().all_pages(page).await?)
+}
+
+/// The title of the most recently closed milestone, or of the open milestone most recently
+/// due, across every configured repository.
+pub async fn latest_milestone() -> Result<String> {
+    let mut milestones = Vec::new();
+    for repo in &config().repositories {
+        milestones.extend(list_milestones(repo).await?);
+    }
+
+    let title = latest_title(milestones, Utc::now().date_naive())
+        .ok_or_else(|| eyre!("No closed or due milestones found"))?;
+    info!("Using milestone {}", title);
+    Ok(title)
+}
+
+/// The title of the milestone closed most recently, or of the open milestone due most recently
+/// by `today`, whichever is later.
+fn latest_title(milestones: Vec<Milestone>, today: NaiveDate) -> Option<String> {
+    let mut latest: Option<(DateTime<Utc>, String)> = None;
+
+    for milestone in milestones {
+        let date = match milestone.state.as_deref() {
+            Some("closed") => milestone.closed_at,
+            _ => milestone.due_on.filter(|due| due.date_naive() <= today),
+        };
+
+        if let Some(date) = date {
+            if latest.as_ref().is_none_or(|(latest, _)| date > *latest) {
+                latest = Some((date, milestone.title));
+            }
+        }
+    }
+
+    latest.map(|(_, title)| title)
+}
+
+/// Prints every milestone in the configured repositories, newest first.
+pub async fn print_milestones() -> Result<i32> {
+    for repo in &config().repositories {
+        let mut milestones = list_milestones(repo).await?;
+        milestones.sort_by_key(|m| std::cmp::Reverse(m.number));
+
+        println!("{}", repo);
+        for milestone in milestones {
+            println!(
+                "  {:<20} {:<6} {:>4} open {:>4} closed   {}",
+                milestone.title,
+                milestone.state.as_deref().unwrap_or("open"),
+                milestone.open_issues.unwrap_or(0),
+                milestone.closed_issues.unwrap_or(0),
+                milestone
+                    .due_on
+                    .map(|due| format!("due {}", due.format("%Y-%m-%d")))
+                    .unwrap_or_else(|| "no due date".to_string())
+            );
+        }
+    }
+
+    Ok(0)
+}
+
+/// A search query for the items in the milestone titled `title` in `repo`. The title is quoted, as
+/// titles such as `Release 1.11` contain spaces.
+pub fn milestone_query(title: &str, repo: &str, qualifiers: &str) -> String {
+    format!("milestone:\"{}\" repo:{} {}", title, repo, qualifiers)
+}
+
+pub async fn find_milestone(repo: &str, title: &str) -> Result<Option<Milestone>> {
+    Ok(list_milestones(repo)
+        .await?
+        .into_iter()
+        .find(|milestone| milestone.title == title))
+}
+
+/// Moves every open issue and pull request in the `from` milestone to the milestone titled `to`,
+/// printing each move. Without the number of the `to` milestone, as in a dry run, only prints
+/// what would have been moved.
+pub async fn move_open_items(
+    repo: &str,
+    from: &str,
+    to: &str,
+    to_number: Option<u64>,
+) -> Result<usize> {
+    let items = search(&milestone_query(from, repo, "is:open")).await?;
+
+    for item in &items {
+        let Some(to_number) = to_number else {
+            println!(
+                "Would move #{} {} from {} to {}",
+                item.number,
+                item.title.trim(),
+                from,
+                to
+            );
+            continue;
+        };
+
+        let (owner, name) = owner_and_name(repo);
+        client()
+            .issues(owner, name)
+            .update(item.number)
+            .milestone(to_number)
+            .send()
+            .await?;
+        println!(
+            "Moved #{} {} from {} to {}",
+            item.number,
+            item.title.trim(),
+            from,
+            to
+        );
+    }
+
+    Ok(items.len())
+}
+
+/// Moves the open work left in each of `versions` into the milestone titled `to`.
+pub async fn carry_over(versions: &[String], to: &str, dry_run: bool) -> Result<i32> {
+    if versions.is_empty() {
+        return Err(eyre!("At least one --milestone is required"));
+    }
+
+    let mut carried = false;
+    for repo in &config().repositories {
+        let Some(target) = find_milestone(repo, to).await? else {
+            warn!("No milestone named {} in {}, so it is skipped", to, repo);
+            continue;
+        };
+        carried = true;
+
+        for version in versions {
+            let target_number = (!dry_run).then_some(target.number as u64);
+            let moved = move_open_items(repo, version, &target.title, target_number).await?;
+            info!(
+                "{} open item(s) in {} of {} carried over to {}",
+                moved, version, repo, to
+            );
+        }
+    }
+
+    if !carried {
+        return Err(eyre!("No milestone named {} in any repository", to));
+    }
+
+    Ok(0)
+}
+
+#[derive(Serialize)]
+struct NewMilestone<'a> {
+    title: &'a str,
+    due_on: String,
+}
+
+#[derive(Serialize)]
+struct MilestoneState {
+    state: &'static str,
+}
+
+/// Closes the released milestones, creates the next one and moves the open work into it,
+/// printing every action taken. With `dry_run`, only prints what would have been done.
+pub async fn rollover(
+    versions: &[String],
+    next: Option<String>,
+    due: Option<NaiveDate>,
+    dry_run: bool,
+) -> Result<i32> {
+    if versions.is_empty() {
+        return Err(eyre!("At least one --milestone is required"));
+    }
+
+    let mut rolled_over = false;
+    for repo in &config().repositories {
+        println!("Rolling over {}", repo);
+        rolled_over |= rollover_repo(repo, versions, next.clone(), due, dry_run).await?;
+    }
+
+    if !rolled_over {
+        return Err(eyre!(
+            "None of the milestones {} exist in any repository",
+            versions.join(", ")
+        ));
+    }
+
+    Ok(0)
+}
+
+/// Rolls over the milestones of one repository, returning whether it had any of `versions`;
+/// repositories without them are skipped.
+async fn rollover_repo(
+    repo: &str,
+    versions: &[String],
+    next: Option<String>,
+    due: Option<NaiveDate>,
+    dry_run: bool,
+) -> Result<bool> {
+    let settings = &config().rollover;
+    let milestones = list_milestones(repo).await?;
+    let mut released = Vec::new();
+    for version in versions {
+        match milestones.iter().find(|m| &m.title == version) {
+            Some(milestone) => released.push(milestone.clone()),
+            None => warn!(
+                "No milestone named {} in {}, so it is skipped",
+                version, repo
+            ),
+        }
+    }
+    if released.is_empty() {
+        return Ok(false);
+    }
+
+    let due = due.unwrap_or_else(|| {
+        let base = released
+            .iter()
+            .filter_map(|m| m.due_on)
+            .max()
+            .map(|d| d.date_naive())
+            .unwrap_or_else(|| Utc::now().date_naive());
+        base + Duration::days(settings.due_in_days)
+    });
+    let title = match next {
+        Some(title) => title,
+        None => next_title(&released, settings.scheme, &settings.date_format, due).await?,
+    };
+
+    if dry_run {
+        println!("Dry run: no changes will be made");
+    }
+
+    let existing = milestones.iter().find(|m| m.title == title).cloned();
+    let target = match existing {
+        Some(milestone) => {
+            println!(
+                "Using existing milestone {} (#{})",
+                milestone.title, milestone.number
+            );
+            Some(milestone)
+        }
+        None if dry_run => {
+            println!("Would create milestone {} due {}", title, due);
+            None
+        }
+        None => {
+            let milestone: Milestone = client()
+                .post(
+                    format!("/repos/{}/milestones", repo),
+                    Some(&NewMilestone {
+                        title: &title,
+                        due_on: due.format("%Y-%m-%dT00:00:00Z").to_string(),
+                    }),
+                )
+                .await?;
+            println!(
+                "Created milestone {} (#{}) due {}",
+                milestone.title, milestone.number, due
+            );
+            Some(milestone)
+        }
+    };
+
+    let target_number = target
+        .filter(|_| !dry_run)
+        .map(|milestone| milestone.number as u64);
+
+    for milestone in &released {
+        move_open_items(repo, &milestone.title, &title, target_number).await?;
+
+        if milestone.state.as_deref() == Some("closed") {
+            println!("Milestone {} is already closed", milestone.title);
+        } else if dry_run {
+            println!(
+                "Would close milestone {} (#{})",
+                milestone.title, milestone.number
+            );
+        } else {
+            let _: Milestone = client()
+                .patch(
+                    format!("/repos/{}/milestones/{}", repo, milestone.number),
+                    Some(&MilestoneState { state: "closed" }),
+                )
+                .await?;
+            println!(
+                "Closed milestone {} (#{})",
+                milestone.title, milestone.number
+            );
+        }
+    }
+
+    Ok(true)
+}
+
+/// Derives the next milestone title from the highest released version under the given scheme.
+async fn next_title(
+    released: &[Milestone],
+    scheme: NamingScheme,
+    date_format: &str,
+    due: NaiveDate,
+) -> Result<String> {
+    let bump: fn(u64, u64, Option<u64>) -> String = match scheme {
+        NamingScheme::Date => return Ok(due.format(date_format).to_string()),
+        NamingScheme::Major => |major, _, patch| match patch {
+            Some(_) => format!("{}.0.0", major + 1),
+            None => format!("{}.0", major + 1),
+        },
+        NamingScheme::Minor => |major, minor, patch| match patch {
+            Some(_) => format!("{}.{}.0", major, minor + 1),
+            None => format!("{}.{}", major, minor + 1),
+        },
+        NamingScheme::Patch => {
+            |major, minor, patch| format!("{}.{}.{}", major, minor, patch.unwrap_or(0) + 1)
+        }
+    };
+
+    let version_regexp = version_regexp().await?;
+    let (title, captures) = released
+        .iter()
+        .filter_map(|m| Some((m.title.as_str(), version_regexp.captures(&m.title)?)))
+        .max_by_key(|(_, c)| {
+            [c.get(1), c.get(2), c.get(3)]
+                .map(|n| n.and_then(|n| n.as_str().parse::<u64>().ok()).unwrap_or(0))
+        })
+        .ok_or_else(|| eyre!("No version number found in the released milestone titles"))?;
+
+    let number = |i: usize| captures.get(i).and_then(|n| n.as_str().parse::<u64>().ok());
+    let (major, minor, patch) = (number(1).unwrap_or(0), number(2).unwrap_or(0), number(3));
+
+    let version = bump(major, minor, patch);
+    let range = captures
+        .get(0)
+        .expect("Capture 0 is always present")
+        .range();
+    Ok(format!(
+        "{}{}{}",
+        &title[..range.start],
+        version,
+        &title[range.end..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn milestone(title: &str) -> Milestone {
+        serde_json::from_value(serde_json::json!({
+            "url": "https://api.github.com/repos/owner/repo/milestones/1",
+            "html_url": "https://github.com/owner/repo/milestone/1",
+            "id": 1,
+            "node_id": "MI_1",
+            "number": 1,
+            "title": title,
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn dated(title: &str, state: &str, closed_at: Option<&str>, due_on: Option<&str>) -> Milestone {
+        let mut milestone = milestone(title);
+        milestone.state = Some(state.to_string());
+        milestone.closed_at = closed_at.map(|date| date.parse().unwrap());
+        milestone.due_on = due_on.map(|date| date.parse().unwrap());
+        milestone
+    }
+
+    async fn next(titles: &[&str], scheme: NamingScheme) -> Result<String> {
+        let released: Vec<Milestone> = titles.iter().map(|title| milestone(title)).collect();
+        let due = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        next_title(&released, scheme, "%Y-%m-%d", due).await
+    }
+
+    #[test]
+    fn quotes_milestone_titles_in_queries() {
+        assert_eq!(
+            milestone_query("Release 1.11", "owner/repo", "is:open"),
+            r#"milestone:"Release 1.11" repo:owner/repo is:open"#
+        );
+    }
+
+    #[test]
+    fn picks_the_latest_closed_or_due_milestone() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let closed = dated("Release 1.2", "closed", Some("2024-03-01T12:00:00Z"), None);
+        let due = dated("Release 1.3", "open", None, Some("2024-03-10T00:00:00Z"));
+        let upcoming = dated("Release 1.4", "open", None, Some("2024-03-20T00:00:00Z"));
+        let undated = dated("Backlog", "open", None, None);
+
+        assert_eq!(
+            latest_title(
+                vec![closed.clone(), due, upcoming.clone(), undated.clone()],
+                today
+            ),
+            Some("Release 1.3".to_string())
+        );
+        assert_eq!(
+            latest_title(vec![upcoming.clone(), closed, undated.clone()], today),
+            Some("Release 1.2".to_string())
+        );
+        assert_eq!(latest_title(vec![upcoming, undated], today), None);
+    }
+
+    #[test]
+    fn closed_milestones_count_from_when_they_were_closed() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let closed_late = dated(
+            "Release 1.2",
+            "closed",
+            Some("2024-03-12T09:00:00Z"),
+            Some("2024-03-01T00:00:00Z"),
+        );
+        let due = dated("Release 1.3", "open", None, Some("2024-03-10T00:00:00Z"));
+
+        assert_eq!(
+            latest_title(vec![due, closed_late], today),
+            Some("Release 1.2".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn bumps_the_highest_released_version() {
+        let titles = ["Release 1.9", "Release 1.10", "Release 1.2"];
+        assert_eq!(
+            next(&titles, NamingScheme::Minor).await.unwrap(),
+            "Release 1.11"
+        );
+        assert_eq!(
+            next(&titles, NamingScheme::Major).await.unwrap(),
+            "Release 2.0"
+        );
+        assert_eq!(
+            next(&titles, NamingScheme::Patch).await.unwrap(),
+            "Release 1.10.1"
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_three_part_versions() {
+        let titles = ["2.3.4"];
+        assert_eq!(next(&titles, NamingScheme::Major).await.unwrap(), "3.0.0");
+        assert_eq!(next(&titles, NamingScheme::Minor).await.unwrap(), "2.4.0");
+        assert_eq!(next(&titles, NamingScheme::Patch).await.unwrap(), "2.3.5");
+    }
+
+    #[tokio::test]
+    async fn names_date_milestones_by_due_date() {
+        assert_eq!(
+            next(&["Sprint"], NamingScheme::Date).await.unwrap(),
+            "2024-03-15"
+        );
+    }
+
+    #[tokio::test]
+    async fn needs_a_versioned_title() {
+        assert!(next(&["Sprint"], NamingScheme::Minor).await.is_err());
+    }
+}
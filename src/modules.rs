@@ -0,0 +1,235 @@
+use crate::{
+    config::{config, ModuleConfig, ModuleSource},
+    links::{changed_files, LinkedPull},
+};
+use color_eyre::{eyre::eyre, Report, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    fs,
+};
+use tokio::sync::OnceCell;
+
+static MODULE_MATCHER: OnceCell<ModuleMatcher> = OnceCell::const_new();
+
+/// Maps changed file paths onto the modules configured for them.
+pub struct ModuleMatcher {
+    modules: Vec<(String, GlobSet)>,
+}
+
+impl ModuleMatcher {
+    fn from_config(module_config: &ModuleConfig) -> Result<ModuleMatcher> {
+        let mut patterns: BTreeMap<String, Vec<String>> = module_config.paths.clone();
+
+        if let Some(path) = &module_config.codeowners {
+            let contents = fs::read_to_string(path)
+                .map_err(|e| eyre!("Unable to read {}: {}", path.display(), e))?;
+
+            for (pattern, module) in codeowners_entries(&contents) {
+                patterns.entry(module).or_default().push(pattern);
+            }
+        }
+
+        let modules = patterns
+            .into_iter()
+            .map(|(module, globs)| {
+                let mut builder = GlobSetBuilder::new();
+                for glob in globs {
+                    builder.add(Glob::new(&glob)?);
+                }
+                Ok::<(String, GlobSet), Report>((module, builder.build()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ModuleMatcher { modules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Every module owning at least one of `paths`, sorted by name.
+    pub fn modules_for(&self, paths: &[String]) -> Vec<String> {
+        self.modules
+            .iter()
+            .filter(|(_, globs)| paths.iter().any(|path| globs.is_match(path)))
+            .map(|(module, _)| module.clone())
+            .collect()
+    }
+}
+
+pub async fn module_matcher() -> Result<&'static ModuleMatcher> {
+    MODULE_MATCHER
+        .get_or_try_init(|| async { ModuleMatcher::from_config(&config().modules) })
+        .await
+}
+
+/// Whether any of the configured module sources needs the changed files of linked pull requests.
+pub async fn inference_enabled() -> Result<bool> {
+    Ok(config().modules.source != ModuleSource::Declared && !module_matcher().await?.is_empty())
+}
+
+/// Modules owning the files changed by the pull requests linked to an issue.
+/// Changed files are cached by pull request number, since one PR often closes several issues.
+pub async fn inferred_modules(
+    repo: &str,
+    pulls: &[LinkedPull],
+    files_cache: &mut HashMap<u64, Vec<String>>,
+) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+
+    for pull in pulls {
+        let files = match files_cache.entry(pull.number) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(changed_files(repo, pull.number).await?),
+        };
+        paths.extend(files.iter().cloned());
+    }
+
+    Ok(module_matcher().await?.modules_for(&paths))
+}
+
+/// Combines the modules declared on the issue with those inferred from its pull requests,
+/// according to the configured `modules.source`.
+pub fn resolve_modules(declared: Vec<String>, inferred: Vec<String>) -> Vec<String> {
+    match config().modules.source {
+        ModuleSource::Declared => declared,
+        ModuleSource::Inferred if inferred.is_empty() => declared,
+        ModuleSource::Inferred => inferred,
+        ModuleSource::Both => {
+            let mut modules: Vec<String> = declared
+                .into_iter()
+                .filter(|m| inferred.is_empty() || m != "Unsure/Other")
+                .collect();
+            for module in inferred {
+                if !modules.contains(&module) {
+                    modules.push(module);
+                }
+            }
+            modules
+        }
+    }
+}
+
+/// Parses `<pattern> <Module Name>` lines, converting CODEOWNERS path semantics into globs:
+/// a `/` at the start or in the middle anchors the pattern at the repository root, a trailing `/`
+/// matches everything beneath that directory, and a bare path matches either a file or a
+/// directory of that name.
+fn codeowners_entries(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (pattern, module) = line.split_once(char::is_whitespace)?;
+            let module = module.trim();
+            if module.is_empty() {
+                return None;
+            }
+
+            let anchored = pattern.trim_end_matches('/').contains('/');
+            let mut glob = pattern.trim_start_matches('/').to_string();
+            if glob.ends_with('/') {
+                glob.push_str("**");
+            } else if !glob.contains('*') {
+                glob = format!("{{{0},{0}/**}}", glob);
+            }
+            if !anchored && !glob.starts_with("**/") {
+                glob = format!("**/{}", glob);
+            }
+
+            Some((glob, module.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FILES: AtomicUsize = AtomicUsize::new(0);
+
+    fn matcher(codeowners: &str, paths: &[(&str, &str)]) -> ModuleMatcher {
+        let file = std::env::temp_dir().join(format!(
+            "release_bot_codeowners_{}_{}",
+            std::process::id(),
+            FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&file, codeowners).unwrap();
+
+        let mut module_config = ModuleConfig {
+            codeowners: Some(file.clone()),
+            ..ModuleConfig::default()
+        };
+        for (module, glob) in paths {
+            module_config
+                .paths
+                .entry(module.to_string())
+                .or_default()
+                .push(glob.to_string());
+        }
+
+        let matcher = ModuleMatcher::from_config(&module_config).unwrap();
+        fs::remove_file(file).unwrap();
+        matcher
+    }
+
+    fn modules(matcher: &ModuleMatcher, path: &str) -> Vec<String> {
+        matcher.modules_for(&[path.to_string()])
+    }
+
+    #[test]
+    fn converts_codeowners_patterns_into_globs() {
+        let entries = codeowners_entries(
+            "# comment\n\n/app/auctions/ Auctions\ndocs Documentation\n*.rb Ruby Code\norphan\n",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                ("app/auctions/**".to_string(), "Auctions".to_string()),
+                ("**/{docs,docs/**}".to_string(), "Documentation".to_string()),
+                ("**/*.rb".to_string(), "Ruby Code".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn anchored_patterns_only_match_from_the_root() {
+        let matcher = matcher("/app/auctions/ Auctions\n", &[]);
+        assert_eq!(modules(&matcher, "app/auctions/lot.rb"), ["Auctions"]);
+        assert!(modules(&matcher, "engines/app/auctions/lot.rb").is_empty());
+    }
+
+    #[test]
+    fn patterns_with_a_slash_in_the_middle_are_anchored() {
+        let matcher = matcher("docs/api API Docs\n", &[]);
+        assert_eq!(modules(&matcher, "docs/api/x.rs"), ["API Docs"]);
+        assert!(modules(&matcher, "src/docs/api/x.rs").is_empty());
+    }
+
+    #[test]
+    fn bare_paths_match_files_and_directories_anywhere() {
+        let matcher = matcher("docs Documentation\n", &[]);
+        assert_eq!(modules(&matcher, "docs"), ["Documentation"]);
+        assert_eq!(
+            modules(&matcher, "engines/docs/guide.md"),
+            ["Documentation"]
+        );
+        assert!(modules(&matcher, "documents/guide.md").is_empty());
+    }
+
+    #[test]
+    fn combines_codeowners_with_configured_paths() {
+        let matcher = matcher(
+            "/app/sim/ SIM\n",
+            &[("Auctions", "app/auctions/**"), ("SIM", "lib/sim/**")],
+        );
+        let paths = [
+            "app/auctions/lot.rb".to_string(),
+            "lib/sim/survey.rb".to_string(),
+        ];
+        assert_eq!(matcher.modules_for(&paths), ["Auctions", "SIM"]);
+        assert_eq!(modules(&matcher, "app/sim/form.rb"), ["SIM"]);
+    }
+}
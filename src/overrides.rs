@@ -0,0 +1,378 @@
+use crate::{config::config, escape, IssueData, Ticket};
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, DirBuilder},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+/// The sections a ticket can be placed in by hand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketSection {
+    ClientRequests,
+    Features,
+    Bugfixes,
+}
+
+/// Corrections to how one ticket is reported, for when its issue was classified wrongly.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TicketOverride {
+    /// The issue number, or 0 for a commit which was not merged from a pull request.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub number: u64,
+    /// The hash, in full or abbreviated, of a commit which was not merged from a pull request,
+    /// for `--git` reports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<TicketSection>,
+    /// Leaves the ticket out of the report and its statistics.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hide: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Makes the ticket a client request from these clients, unless `section` says otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clients: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modules: Option<Vec<String>>,
+}
+
+impl TicketOverride {
+    /// An override for the ticket which changes nothing yet.
+    pub fn for_ticket(number: u64, commit: Option<&str>) -> TicketOverride {
+        TicketOverride {
+            number,
+            commit: commit.filter(|_| number == 0).map(String::from),
+            ..TicketOverride::default()
+        }
+    }
+
+    /// Whether the override is for the ticket with this number and commit. Tickets are matched
+    /// by number, or by hash when they have none, as every such commit is numbered 0.
+    pub fn is_for(&self, number: u64, commit: Option<&str>) -> bool {
+        match (&self.commit, commit) {
+            (Some(hash), Some(commit)) if number == 0 => {
+                !hash.is_empty() && commit.starts_with(hash.as_str())
+            }
+            (None, _) => number != 0 && number == self.number,
+            _ => false,
+        }
+    }
+
+    /// How the ticket is named in log messages.
+    fn reference(&self) -> String {
+        match &self.commit {
+            Some(hash) => hash.clone(),
+            None => format!("#{}", self.number),
+        }
+    }
+}
+
+fn is_zero(number: &u64) -> bool {
+    *number == 0
+}
+
+/// The overrides for one release, read from a TOML or YAML file with a `tickets` list.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Overrides {
+    pub tickets: Vec<TicketOverride>,
+}
+
+impl Overrides {
+    /// Where the overrides for the release with this file stem are kept by default: the first of
+    /// `overrides-<stem>.toml`, `.yaml` or `.yml` in the configured directory which exists.
+    pub fn default_path(stem: &str) -> PathBuf {
+        let directory = &config().overrides.directory;
+        ["toml", "yaml", "yml"]
+            .iter()
+            .map(|extension| directory.join(format!("overrides-{}.{}", stem, extension)))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| directory.join(format!("overrides-{}.toml", stem)))
+    }
+
+    /// Reads the overrides at `path`; a missing file has none.
+    pub fn load(path: &Path) -> Result<Overrides> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Overrides::default()),
+            Err(e) => return Err(eyre!("Unable to read {}: {}", path.display(), e)),
+        };
+
+        let overrides = if is_yaml(path) {
+            serde_yaml::from_str(&contents).map_err(|e| eyre!("{}", e))
+        } else {
+            toml::from_str(&contents).map_err(|e| eyre!("{}", e))
+        };
+
+        overrides.map_err(|e| eyre!("Invalid overrides file {}: {}", path.display(), e))
+    }
+
+    /// Writes the overrides to `path`, as YAML or TOML depending on its extension.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            DirBuilder::new().recursive(true).create(parent)?;
+        }
+
+        let contents = if is_yaml(path) {
+            serde_yaml::to_string(self)?
+        } else {
+            toml::to_string_pretty(self)?
+        };
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Moves, hides, retitles or reclassifies the tickets in `issues` as the overrides say,
+    /// keeping the module statistics in step, and records each override applied.
+    pub fn apply(&self, issues: &mut IssueData) {
+        for ticket_override in &self.tickets {
+            let Some((section, mut ticket)) = issues.take_ticket(ticket_override) else {
+                warn!(
+                    "Override for {} does not match any ticket in this release",
+                    ticket_override.reference()
+                );
+                continue;
+            };
+            issues.count_modules(&ticket, false);
+            issues.overrides.push(ticket_override.clone());
+
+            if ticket_override.hide {
+                info!("Hiding {}", ticket.reference());
+                continue;
+            }
+
+            let mut target = section;
+            let clients = ticket_override.clients.as_ref().filter(|c| !c.is_empty());
+            if let Some(title) = &ticket_override.title {
+                ticket.title = escape(title.trim());
+                ticket.release_notes = None;
+            }
+            if let Some(modules) = &ticket_override.modules {
+                ticket.modules = modules.clone();
+            }
+            if let Some(clients) = clients {
+                ticket.detail = escape(&clients.join(", "));
+                target = TicketSection::ClientRequests;
+            }
+            if let Some(forced) = ticket_override.section {
+                target = forced;
+                match forced {
+                    TicketSection::Features => ticket.feature = true,
+                    TicketSection::Bugfixes => ticket.feature = false,
+                    TicketSection::ClientRequests => {}
+                }
+            }
+            if target != TicketSection::ClientRequests {
+                ticket.detail = ticket.author.clone();
+            } else if section != TicketSection::ClientRequests && clients.is_none() {
+                warn!(
+                    "Override moves {} into client requests without naming any clients",
+                    ticket.reference()
+                );
+                ticket.detail = String::new();
+            }
+
+            issues.count_modules(&ticket, true);
+            issues.section_mut(target).push(ticket);
+        }
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml") | Some("yml")
+    )
+}
+
+impl IssueData {
+    fn section_mut(&mut self, section: TicketSection) -> &mut Vec<Ticket> {
+        match section {
+            TicketSection::ClientRequests => &mut self.client_requests,
+            TicketSection::Features => &mut self.features,
+            TicketSection::Bugfixes => &mut self.bugfixes,
+        }
+    }
+
+    /// Removes the ticket the override is for from whichever section it is in.
+    fn take_ticket(&mut self, ticket_override: &TicketOverride) -> Option<(TicketSection, Ticket)> {
+        [
+            TicketSection::ClientRequests,
+            TicketSection::Features,
+            TicketSection::Bugfixes,
+        ]
+        .into_iter()
+        .find_map(|section| {
+            let tickets = self.section_mut(section);
+            let index = tickets
+                .iter()
+                .position(|t| ticket_override.is_for(t.number, t.commit.as_deref()))?;
+            Some((section, tickets.remove(index)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(number: u64, module: &str, feature: bool) -> Ticket {
+        Ticket {
+            detail: "octocat".to_string(),
+            author: "octocat".to_string(),
+            modules: vec![module.to_string()],
+            feature,
+            ..Ticket::fixture(number, "Original title")
+        }
+    }
+
+    fn issues() -> IssueData {
+        let mut issues = IssueData {
+            client_requests: vec![Ticket {
+                detail: "Acme".to_string(),
+                ..ticket(1, "Auctions", false)
+            }],
+            features: vec![ticket(2, "Auctions", true)],
+            bugfixes: vec![ticket(3, "SIM", false)],
+            ..IssueData::default()
+        };
+        issues.recount_modules();
+        issues
+    }
+
+    fn apply(tickets: Vec<TicketOverride>) -> IssueData {
+        let mut issues = issues();
+        Overrides { tickets }.apply(&mut issues);
+        issues
+    }
+
+    #[test]
+    fn moves_tickets_between_sections() {
+        let issues = apply(vec![TicketOverride {
+            number: 3,
+            section: Some(TicketSection::Features),
+            ..TicketOverride::default()
+        }]);
+
+        assert!(issues.bugfixes.is_empty());
+        assert_eq!(issues.features.len(), 2);
+        assert!(issues.features[1].feature);
+        assert_eq!(issues.module_stats["SIM"].features, 1);
+        assert_eq!(issues.module_stats["SIM"].bugs, 0);
+        assert_eq!(issues.overrides.len(), 1);
+    }
+
+    #[test]
+    fn hides_tickets_and_their_statistics() {
+        let issues = apply(vec![TicketOverride {
+            number: 2,
+            hide: true,
+            ..TicketOverride::default()
+        }]);
+
+        assert!(issues.features.is_empty());
+        assert_eq!(issues.module_stats["Auctions"].features, 0);
+        assert_eq!(issues.module_stats["Auctions"].bugs, 1);
+    }
+
+    #[test]
+    fn retitles_tickets_in_place() {
+        let issues = apply(vec![TicketOverride {
+            number: 3,
+            title: Some(" Fix R&D exports ".to_string()),
+            modules: Some(vec!["Reports".to_string()]),
+            ..TicketOverride::default()
+        }]);
+
+        let ticket = &issues.bugfixes[0];
+        assert_eq!(ticket.title, r"Fix R\&D exports");
+        assert_eq!(ticket.modules, ["Reports"]);
+        assert_eq!(issues.module_stats["SIM"].bugs, 0);
+        assert_eq!(issues.module_stats["Reports"].bugs, 1);
+    }
+
+    #[test]
+    fn makes_client_requests_from_clients() {
+        let issues = apply(vec![TicketOverride {
+            number: 2,
+            clients: Some(vec!["Acme".to_string(), "Globex".to_string()]),
+            ..TicketOverride::default()
+        }]);
+
+        assert_eq!(issues.client_requests[1].detail, "Acme, Globex");
+        assert!(issues.features.is_empty());
+    }
+
+    #[test]
+    fn names_no_clients_when_none_are_given() {
+        let issues = apply(vec![TicketOverride {
+            number: 3,
+            section: Some(TicketSection::ClientRequests),
+            ..TicketOverride::default()
+        }]);
+
+        assert_eq!(issues.client_requests[1].detail, "");
+    }
+
+    #[test]
+    fn shows_the_author_outside_client_requests() {
+        let issues = apply(vec![TicketOverride {
+            number: 1,
+            section: Some(TicketSection::Bugfixes),
+            ..TicketOverride::default()
+        }]);
+
+        assert!(issues.client_requests.is_empty());
+        assert_eq!(issues.bugfixes[1].detail, "octocat");
+    }
+
+    #[test]
+    fn skips_overrides_for_unknown_tickets() {
+        let issues = apply(vec![TicketOverride {
+            number: 99,
+            hide: true,
+            ..TicketOverride::default()
+        }]);
+
+        assert_eq!(issues.features.len(), 1);
+        assert!(issues.overrides.is_empty());
+    }
+
+    #[test]
+    fn tells_commits_without_pull_requests_apart_by_hash() {
+        let commit = |hash: &str, title: &str| Ticket {
+            commit: Some(hash.to_string()),
+            ..Ticket::fixture(0, title)
+        };
+        let mut issues = IssueData {
+            bugfixes: vec![
+                commit("0c36a99d3b1e", "First fix"),
+                commit("7a447b1f0c2d", "Second fix"),
+            ],
+            ..IssueData::default()
+        };
+        Overrides {
+            tickets: vec![
+                TicketOverride {
+                    commit: Some("7a447b1".to_string()),
+                    title: Some("Retitled".to_string()),
+                    ..TicketOverride::default()
+                },
+                TicketOverride {
+                    hide: true,
+                    ..TicketOverride::default()
+                },
+            ],
+        }
+        .apply(&mut issues);
+
+        let titles: Vec<&str> = issues.bugfixes.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["First fix", "Retitled"]);
+        assert_eq!(issues.overrides.len(), 1);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{config::config, escape};
 use color_eyre::{Report, Result};
 use regex::{Regex, RegexBuilder};
 use tokio::sync::OnceCell;
@@ -6,6 +7,14 @@ static OLD_CLIENT_REGEXP: OnceCell<Regex> = OnceCell::const_new();
 static NEW_CLIENT_REGEXP: OnceCell<Regex> = OnceCell::const_new();
 static FEATURE_REGEXP: OnceCell<Regex> = OnceCell::const_new();
 static MODULE_REGEXP: OnceCell<Regex> = OnceCell::const_new();
+static CLOSING_REGEXP: OnceCell<Regex> = OnceCell::const_new();
+static VERSION_REGEXP: OnceCell<Regex> = OnceCell::const_new();
+static MERGE_COMMIT_REGEXP: OnceCell<Regex> = OnceCell::const_new();
+static RELEASE_NOTES_REGEXP: OnceCell<Regex> = OnceCell::const_new();
+static HOTFIX_REGEXP: OnceCell<Regex> = OnceCell::const_new();
+static COMMIT_HEADER_REGEXP: OnceCell<Regex> = OnceCell::const_new();
+static COMMIT_FOOTER_REGEXP: OnceCell<Regex> = OnceCell::const_new();
+static MIGRATION_NOTES_REGEXP: OnceCell<Regex> = OnceCell::const_new();
 
 pub async fn client_details(haystack: &str) -> Option<String> {
     let client_regexp = old_client_regexp().await.ok()?;
@@ -18,13 +27,9 @@ pub async fn client_details(haystack: &str) -> Option<String> {
     };
 
     results
-        .map(|m| m.as_str())
-        .map(|m| {
-            m.replace('\n', ", ")
-                .replace('&', "\\&")
-                .replace('#', "\\#")
-        })
+        .map(|m| m.as_str().replace('\n', ", "))
         .filter(|m| *m != "_No response_" && !m.trim().is_empty())
+        .map(|m| escape(&m))
 }
 
 pub async fn old_client_regexp() -> Result<&'static Regex> {
@@ -67,21 +72,26 @@ pub async fn feature_regexp() -> Result<&'static Regex> {
 
 pub async fn module_details(haystack: &str) -> Option<Vec<&str>> {
     Some(
-        module_regexp()
+        module_answer(haystack)
             .await
-            .ok()?
-            .captures(haystack)
-            .and_then(|c| {
-                c.get(2)
-                    .map(|m| m.as_str())
-                    .filter(|m| *m != "_No response_" && !m.trim().is_empty())
-            })
-            .unwrap_or("Unsure/Other")
-            .split(", ")
-            .collect(),
+            .unwrap_or_else(|| vec!["Unsure/Other"]),
     )
 }
 
+/// The modules listed in answer to the "Which module(s)" question, if it was answered at all.
+pub async fn module_answer(haystack: &str) -> Option<Vec<&str>> {
+    module_regexp()
+        .await
+        .ok()?
+        .captures(haystack)
+        .and_then(|c| {
+            c.get(2)
+                .map(|m| m.as_str())
+                .filter(|m| *m != "_No response_" && !m.trim().is_empty())
+        })
+        .map(|m| m.split(", ").collect())
+}
+
 pub async fn module_regexp() -> Result<&'static Regex> {
     MODULE_REGEXP
         .get_or_try_init(|| async {
@@ -91,3 +101,161 @@ pub async fn module_regexp() -> Result<&'static Regex> {
         })
         .await
 }
+
+/// The text under the release notes heading, if the body has one with an answer.
+pub async fn release_notes(haystack: &str) -> Option<String> {
+    release_notes_regexp()
+        .await
+        .ok()?
+        .captures(haystack)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().trim())
+        .filter(|m| *m != "_No response_" && !m.is_empty())
+        .map(String::from)
+}
+
+pub async fn release_notes_regexp() -> Result<&'static Regex> {
+    RELEASE_NOTES_REGEXP
+        .get_or_try_init(|| async {
+            Ok::<Regex, Report>(
+                RegexBuilder::new(&format!(
+                    r"### {}\n+(.*?)\n*(###|$)",
+                    regex::escape(&config().release_notes.heading)
+                ))
+                .dot_matches_new_line(true)
+                .case_insensitive(true)
+                .build()?,
+            )
+        })
+        .await
+}
+
+/// The text under the migration notes heading, if the body has one with an answer.
+pub async fn migration_notes(haystack: &str) -> Option<String> {
+    migration_notes_regexp()
+        .await
+        .ok()?
+        .captures(haystack)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().trim())
+        .filter(|m| *m != "_No response_" && !m.is_empty())
+        .map(String::from)
+}
+
+pub async fn migration_notes_regexp() -> Result<&'static Regex> {
+    MIGRATION_NOTES_REGEXP
+        .get_or_try_init(|| async {
+            Ok::<Regex, Report>(
+                RegexBuilder::new(&format!(
+                    r"### {}\n+(.*?)\n*(###|$)",
+                    regex::escape(&config().breaking.migration_notes_heading)
+                ))
+                .dot_matches_new_line(true)
+                .case_insensitive(true)
+                .build()?,
+            )
+        })
+        .await
+}
+
+pub async fn closing_references(haystack: &str) -> Vec<u64> {
+    let Ok(closing_regexp) = closing_regexp().await else {
+        return Vec::new();
+    };
+
+    closing_regexp
+        .captures_iter(haystack)
+        .filter_map(|c| c.get(1))
+        .filter_map(|m| m.as_str().parse().ok())
+        .collect()
+}
+
+pub async fn closing_regexp() -> Result<&'static Regex> {
+    CLOSING_REGEXP
+        .get_or_try_init(|| async {
+            Ok::<Regex, Report>(
+                RegexBuilder::new(r"\b(?:close[sd]?|fix(?:e[sd])?|resolve[sd]?):?\s+#(\d+)\b")
+                    .case_insensitive(true)
+                    .build()?,
+            )
+        })
+        .await
+}
+
+/// Matches the keys of hotfix releases, as configured in `delivery.hotfix_pattern`.
+pub async fn hotfix_regexp() -> Result<&'static Regex> {
+    HOTFIX_REGEXP
+        .get_or_try_init(|| async {
+            Ok::<Regex, Report>(Regex::new(&config().delivery.hotfix_pattern)?)
+        })
+        .await
+}
+
+pub async fn version_regexp() -> Result<&'static Regex> {
+    VERSION_REGEXP
+        .get_or_try_init(|| async { Ok::<Regex, Report>(Regex::new(r"(\d+)\.(\d+)(?:\.(\d+))?")?) })
+        .await
+}
+
+/// The pull request a merge commit (`Merge pull request #123 from ...`) or squash commit
+/// (`Subject line (#123)`) came from, judging by the first line of its message.
+pub async fn merged_pull_reference(message: &str) -> Option<u64> {
+    merge_commit_regexp()
+        .await
+        .ok()?
+        .captures(message.lines().next()?)
+        .and_then(|c| c.get(1).or_else(|| c.get(2)))
+        .and_then(|m| m.as_str().parse().ok())
+}
+
+pub async fn merge_commit_regexp() -> Result<&'static Regex> {
+    MERGE_COMMIT_REGEXP
+        .get_or_try_init(|| async {
+            Ok::<Regex, Report>(Regex::new(r"^Merge pull request #(\d+)|\(#(\d+)\)\s*$")?)
+        })
+        .await
+}
+
+/// A Conventional Commits subject line, such as `feat(api)!: add bulk bids (#12)`.
+pub async fn commit_header_regexp() -> Result<&'static Regex> {
+    COMMIT_HEADER_REGEXP
+        .get_or_try_init(|| async {
+            Ok::<Regex, Report>(Regex::new(
+                r"^(?P<kind>[A-Za-z]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: +(?P<description>.+)$",
+            )?)
+        })
+        .await
+}
+
+/// The start of a commit message footer, such as `Refs: 12`, `Closes #12` or
+/// `BREAKING CHANGE: ...`.
+pub async fn commit_footer_regexp() -> Result<&'static Regex> {
+    COMMIT_FOOTER_REGEXP
+        .get_or_try_init(|| async {
+            Ok::<Regex, Report>(Regex::new(r"^(?:BREAKING[ -]CHANGE|[A-Za-z-]+)(?:: | #)")?)
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn finds_every_closing_keyword() {
+        let body = "Closes #12, fixes #34.\nResolved: #56\nfix #7 and close #8";
+        assert_eq!(closing_references(body).await, [12, 34, 56, 7, 8]);
+    }
+
+    #[tokio::test]
+    async fn ignores_plain_mentions() {
+        let body = "Related to #12, see #34 and prefix #56. Unfixed #7.";
+        assert!(closing_references(body).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn ignores_references_to_other_repositories() {
+        assert!(closing_references("Fixes owner/repo#12").await.is_empty());
+        assert!(closing_references("Fixes #12abc").await.is_empty());
+    }
+}
@@ -0,0 +1,390 @@
+use crate::{
+    overrides::{Overrides, TicketOverride, TicketSection},
+    unescape, IssueData,
+};
+use color_eyre::{eyre::eyre, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style, Stylize},
+    widgets::{Block, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use std::path::Path;
+use tracing::info;
+
+const HELP: &str = "↑/↓ select   c client request   f feature   b bug fix   x exclude   \
+                    e edit title   s save and render   q cancel";
+
+/// A ticket as the release manager has left it so far.
+struct Entry {
+    number: u64,
+    /// The hash of a commit which was not merged from a pull request, which has no number.
+    commit: Option<String>,
+    /// How the ticket is shown, such as `#123`.
+    reference: String,
+    /// The issue title, before any override.
+    original_title: String,
+    title: String,
+    /// The section the ticket would be in without a section override.
+    natural_section: TicketSection,
+    section: TicketSection,
+    hidden: bool,
+    modules: String,
+    clients: String,
+}
+
+enum Mode {
+    Browse,
+    EditTitle(String),
+}
+
+struct Review {
+    entries: Vec<Entry>,
+    state: TableState,
+    mode: Mode,
+}
+
+/// Lets the release manager move, exclude and retitle tickets before the reports are rendered,
+/// starting from the decisions already in `overrides`. The decisions are saved to `path` and
+/// returned; cancelling the review stops the run.
+pub fn review(issues: &IssueData, overrides: Overrides, path: &Path) -> Result<Overrides> {
+    let mut review = Review::new(issues, &overrides);
+    if review.entries.is_empty() {
+        return Ok(overrides);
+    }
+
+    let mut terminal = ratatui::init();
+    let signed_off = review.run(&mut terminal);
+    ratatui::restore();
+
+    if !signed_off? {
+        return Err(eyre!("Review cancelled; no reports were generated"));
+    }
+
+    let overrides = review.overrides(overrides);
+    overrides.save(path)?;
+    info!("Saved review decisions to {}", path.display());
+    Ok(overrides)
+}
+
+impl Review {
+    fn new(issues: &IssueData, overrides: &Overrides) -> Self {
+        let mut entries = Vec::new();
+
+        for (section, tickets) in [
+            (TicketSection::ClientRequests, &issues.client_requests),
+            (TicketSection::Features, &issues.features),
+            (TicketSection::Bugfixes, &issues.bugfixes),
+        ] {
+            for ticket in tickets {
+                let existing = overrides
+                    .tickets
+                    .iter()
+                    .find(|o| o.is_for(ticket.number, ticket.commit.as_deref()));
+                let clients = existing
+                    .and_then(|o| o.clients.as_ref())
+                    .filter(|clients| !clients.is_empty());
+                let natural_section = if clients.is_some() {
+                    TicketSection::ClientRequests
+                } else {
+                    section
+                };
+                let original_title = unescape(&ticket.title);
+
+                entries.push(Entry {
+                    number: ticket.number,
+                    commit: ticket.commit.clone().filter(|_| ticket.number == 0),
+                    reference: ticket.reference(),
+                    title: existing
+                        .and_then(|o| o.title.clone())
+                        .unwrap_or_else(|| original_title.clone()),
+                    original_title,
+                    natural_section,
+                    section: existing.and_then(|o| o.section).unwrap_or(natural_section),
+                    hidden: existing.is_some_and(|o| o.hide),
+                    modules: existing
+                        .and_then(|o| o.modules.as_ref())
+                        .unwrap_or(&ticket.modules)
+                        .join(", "),
+                    clients: match clients {
+                        Some(clients) => clients.join(", "),
+                        None if section == TicketSection::ClientRequests => {
+                            unescape(&ticket.detail)
+                        }
+                        None => String::new(),
+                    },
+                });
+            }
+        }
+        entries.sort_by(|a, b| (a.number, &a.commit).cmp(&(b.number, &b.commit)));
+
+        Review {
+            entries,
+            state: TableState::default().with_selected(Some(0)),
+            mode: Mode::Browse,
+        }
+    }
+
+    /// Handles key presses until the review is saved (true) or cancelled (false).
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<bool> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            self.mode = match std::mem::replace(&mut self.mode, Mode::Browse) {
+                Mode::EditTitle(mut title) => match key.code {
+                    KeyCode::Enter => {
+                        let title = title.trim();
+                        if let (false, Some(entry)) = (title.is_empty(), self.selected()) {
+                            entry.title = title.to_string();
+                        }
+                        Mode::Browse
+                    }
+                    KeyCode::Esc => Mode::Browse,
+                    KeyCode::Backspace => {
+                        title.pop();
+                        Mode::EditTitle(title)
+                    }
+                    KeyCode::Char(c) => {
+                        title.push(c);
+                        Mode::EditTitle(title)
+                    }
+                    _ => Mode::EditTitle(title),
+                },
+                Mode::Browse => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                    KeyCode::Char('s') => return Ok(true),
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        self.state.select_next();
+                        Mode::Browse
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        self.state.select_previous();
+                        Mode::Browse
+                    }
+                    KeyCode::Char('c') => self.move_to(TicketSection::ClientRequests),
+                    KeyCode::Char('f') => self.move_to(TicketSection::Features),
+                    KeyCode::Char('b') => self.move_to(TicketSection::Bugfixes),
+                    KeyCode::Char('x') => {
+                        if let Some(entry) = self.selected() {
+                            entry.hidden = !entry.hidden;
+                        }
+                        Mode::Browse
+                    }
+                    KeyCode::Char('e') => match self.selected() {
+                        Some(entry) => Mode::EditTitle(entry.title.clone()),
+                        None => Mode::Browse,
+                    },
+                    _ => Mode::Browse,
+                },
+            };
+        }
+    }
+
+    fn selected(&mut self) -> Option<&mut Entry> {
+        let index = self.state.selected()?.min(self.entries.len() - 1);
+        self.entries.get_mut(index)
+    }
+
+    fn move_to(&mut self, section: TicketSection) -> Mode {
+        if let Some(entry) = self.selected() {
+            entry.section = section;
+        }
+        Mode::Browse
+    }
+
+    fn draw(&mut self, frame: &mut Frame<'_>) {
+        let [table_area, help_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(3)]).areas(frame.area());
+
+        let rows = self.entries.iter().map(|entry| {
+            let row = Row::new(vec![
+                entry.reference.clone(),
+                section_name(entry.section).to_string(),
+                entry.title.clone(),
+                entry.modules.clone(),
+                entry.clients.clone(),
+            ]);
+            if entry.hidden {
+                row.style(Style::new().add_modifier(Modifier::DIM | Modifier::CROSSED_OUT))
+            } else {
+                row
+            }
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(16),
+                Constraint::Fill(3),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(["Ticket", "Section", "Title", "Modules", "Clients"]).bold())
+        .block(Block::bordered().title(" Review release "))
+        .row_highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(table, table_area, &mut self.state);
+
+        let help = match &self.mode {
+            Mode::Browse => HELP.to_string(),
+            Mode::EditTitle(title) => {
+                format!("New title: {}█   Enter to confirm, Esc to cancel", title)
+            }
+        };
+        frame.render_widget(Paragraph::new(help).block(Block::bordered()), help_area);
+    }
+
+    /// `overrides` updated with the decisions made in the review. Overrides which no longer
+    /// change anything are dropped; those for tickets not in this release are kept.
+    fn overrides(&self, mut overrides: Overrides) -> Overrides {
+        for entry in &self.entries {
+            let commit = entry.commit.as_deref();
+            let mut ticket_override = match overrides
+                .tickets
+                .iter()
+                .position(|o| o.is_for(entry.number, commit))
+            {
+                Some(index) => overrides.tickets.remove(index),
+                None => TicketOverride::for_ticket(entry.number, commit),
+            };
+
+            ticket_override.hide = entry.hidden;
+            ticket_override.section =
+                (entry.section != entry.natural_section).then_some(entry.section);
+            ticket_override.title =
+                (entry.title != entry.original_title).then(|| entry.title.clone());
+
+            if ticket_override.hide
+                || ticket_override.section.is_some()
+                || ticket_override.title.is_some()
+                || ticket_override.clients.is_some()
+                || ticket_override.modules.is_some()
+            {
+                overrides.tickets.push(ticket_override);
+            }
+        }
+
+        overrides
+            .tickets
+            .sort_by(|a, b| (a.number, &a.commit).cmp(&(b.number, &b.commit)));
+        overrides
+    }
+}
+
+fn section_name(section: TicketSection) -> &'static str {
+    match section {
+        TicketSection::ClientRequests => "Client request",
+        TicketSection::Features => "Feature",
+        TicketSection::Bugfixes => "Bug fix",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(number: u64, commit: Option<&str>) -> Entry {
+        Entry {
+            number,
+            commit: commit.map(String::from),
+            reference: format!("#{}", number),
+            original_title: "Fix the crash".to_string(),
+            title: "Fix the crash".to_string(),
+            natural_section: TicketSection::Bugfixes,
+            section: TicketSection::Bugfixes,
+            hidden: false,
+            modules: String::new(),
+            clients: String::new(),
+        }
+    }
+
+    fn review(entries: Vec<Entry>) -> Review {
+        Review {
+            entries,
+            state: TableState::default(),
+            mode: Mode::Browse,
+        }
+    }
+
+    #[test]
+    fn drops_edits_which_were_undone() {
+        let mut retitled = entry(1, None);
+        retitled.title = "Fix the crash on start".to_string();
+        let mut moved_back = entry(2, None);
+        moved_back.section = TicketSection::Bugfixes;
+        let mut shown_again = entry(3, None);
+        shown_again.hidden = false;
+
+        let existing = Overrides {
+            tickets: vec![
+                TicketOverride {
+                    section: Some(TicketSection::Features),
+                    ..TicketOverride::for_ticket(2, None)
+                },
+                TicketOverride {
+                    hide: true,
+                    ..TicketOverride::for_ticket(3, None)
+                },
+            ],
+        };
+        let overrides = review(vec![retitled, moved_back, shown_again]).overrides(existing);
+
+        assert_eq!(overrides.tickets.len(), 1);
+        assert_eq!(overrides.tickets[0].number, 1);
+        assert_eq!(
+            overrides.tickets[0].title.as_deref(),
+            Some("Fix the crash on start")
+        );
+        assert_eq!(overrides.tickets[0].section, None);
+    }
+
+    #[test]
+    fn keeps_overrides_for_tickets_outside_the_release() {
+        let existing = Overrides {
+            tickets: vec![
+                TicketOverride {
+                    hide: true,
+                    ..TicketOverride::for_ticket(9, None)
+                },
+                TicketOverride {
+                    clients: Some(vec!["Acme".to_string()]),
+                    ..TicketOverride::for_ticket(1, None)
+                },
+            ],
+        };
+        let overrides = review(vec![entry(1, None)]).overrides(existing);
+
+        let numbers: Vec<u64> = overrides.tickets.iter().map(|o| o.number).collect();
+        assert_eq!(numbers, [1, 9]);
+        assert_eq!(
+            overrides.tickets[0].clients.as_deref(),
+            Some(&["Acme".to_string()][..])
+        );
+        assert!(overrides.tickets[1].hide);
+    }
+
+    #[test]
+    fn keeps_commits_without_pull_requests_apart() {
+        let mut hidden = entry(0, Some("0c36a99d2f1e"));
+        hidden.hidden = true;
+        let mut moved = entry(0, Some("5be1a8c7740d"));
+        moved.section = TicketSection::Features;
+
+        let overrides = review(vec![moved, hidden]).overrides(Overrides::default());
+
+        assert_eq!(overrides.tickets.len(), 2);
+        assert_eq!(overrides.tickets[0].commit.as_deref(), Some("0c36a99d2f1e"));
+        assert!(overrides.tickets[0].hide);
+        assert_eq!(overrides.tickets[1].commit.as_deref(), Some("5be1a8c7740d"));
+        assert_eq!(overrides.tickets[1].section, Some(TicketSection::Features));
+        assert!(!overrides.tickets[1].hide);
+    }
+}
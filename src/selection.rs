@@ -0,0 +1,230 @@
+use crate::{
+    client,
+    commits::committed_at,
+    get_issue, is_not_found,
+    milestones::{find_milestone, milestone_query},
+    owner_and_name,
+    regex::{closing_references, merged_pull_reference},
+    search, search_closed, ClosedIssue,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use color_eyre::Result;
+use octocrab::models::{issues::Issue, IssueState};
+use std::{collections::BTreeSet, path::PathBuf};
+use tracing::warn;
+
+/// The body of work a report covers.
+#[derive(Clone, Debug)]
+pub enum Selection {
+    /// Everything closed in the milestone with this title.
+    Milestone(String),
+    /// Issues closed and pull requests merged between two dates, inclusive.
+    DateRange { since: NaiveDate, until: NaiveDate },
+    /// Pull requests merged between two tags, and the issues they closed.
+    TagRange { from: String, to: String },
+    /// Conventional Commits made after one ref up to another in a local repository, read
+    /// without GitHub.
+    Commits {
+        repository: PathBuf,
+        from: String,
+        to: String,
+    },
+}
+
+impl Selection {
+    /// How the selection is named in report titles.
+    pub fn name(&self) -> String {
+        match self {
+            Selection::Milestone(version) => format!("v{}", version),
+            Selection::DateRange { since, until } => format!("{} to {}", since, until),
+            Selection::TagRange { from, to } | Selection::Commits { from, to, .. } => {
+                format!("{} to {}", from, to)
+            }
+        }
+    }
+
+    /// How the selection is named in report file names.
+    pub fn file_stem(&self) -> String {
+        match self {
+            Selection::Milestone(version) => version.clone(),
+            Selection::DateRange { since, until } => format!("{}_{}", since, until),
+            Selection::TagRange { from, to } | Selection::Commits { from, to, .. } => {
+                format!("{}_{}", from, to)
+            }
+        }
+    }
+
+    pub fn is_milestone(&self) -> bool {
+        matches!(self, Selection::Milestone(_))
+    }
+
+    /// Closed issues (not pull requests) within the selection.
+    pub async fn closed_issues(&self, repo: &str) -> Result<Vec<ClosedIssue>> {
+        match self {
+            Selection::Milestone(version) => {
+                search_closed(&milestone_query(version, repo, "is:closed is:issue")).await
+            }
+            Selection::DateRange { since, until } => {
+                search_closed(&format!(
+                    "repo:{} is:closed is:issue closed:{}..{}",
+                    repo, since, until
+                ))
+                .await
+            }
+            Selection::TagRange { .. } => {
+                let mut numbers = BTreeSet::new();
+                for pull in self.merged_pulls(repo).await? {
+                    let body = pull.body.unwrap_or_default();
+                    numbers.extend(closing_references(&body).await);
+                }
+
+                let mut issues = Vec::new();
+                for number in numbers {
+                    let closed = get_issue(repo, number).await?;
+                    if closed.issue.pull_request.is_none()
+                        && closed.issue.state == IssueState::Closed
+                    {
+                        issues.push(closed);
+                    }
+                }
+                Ok(issues)
+            }
+            // Commits are read from the repository by `commits::read`.
+            Selection::Commits { .. } => Ok(Vec::new()),
+        }
+    }
+
+    /// Merged pull requests within the selection.
+    pub async fn merged_pulls(&self, repo: &str) -> Result<Vec<Issue>> {
+        match self {
+            Selection::Milestone(version) => {
+                search(&milestone_query(version, repo, "is:pr is:merged")).await
+            }
+            Selection::DateRange { since, until } => {
+                search(&format!(
+                    "repo:{} is:pr is:merged merged:{}..{}",
+                    repo, since, until
+                ))
+                .await
+            }
+            Selection::TagRange { from, to } => {
+                let (owner, name) = owner_and_name(repo);
+                let mut pulls = Vec::new();
+                for number in tag_range_pulls(repo, from, to).await? {
+                    let pull = client().issues(owner, name).get(number).await?;
+                    if pull.pull_request.is_some() {
+                        pulls.push(pull);
+                    }
+                }
+                Ok(pulls)
+            }
+            Selection::Commits { .. } => Ok(Vec::new()),
+        }
+    }
+
+    /// When the selection was released in `repo`: when its milestone was closed, when the `to`
+    /// ref was committed, or the end of its date range. None if the milestone is still open, or
+    /// the milestone or ref is not in `repo`.
+    pub async fn released_at(&self, repo: &str) -> Result<Option<DateTime<Utc>>> {
+        match self {
+            Selection::Milestone(version) => Ok(find_milestone(repo, version)
+                .await?
+                .and_then(|milestone| milestone.closed_at)),
+            Selection::DateRange { until, .. } => {
+                Ok(until.and_hms_opt(23, 59, 59).map(|end| end.and_utc()))
+            }
+            Selection::TagRange { to, .. } => {
+                let (owner, name) = owner_and_name(repo);
+                match client().commits(owner, name).get(to).await {
+                    Ok(commit) => Ok(commit.commit.committer.and_then(|committer| committer.date)),
+                    Err(e) if is_not_found(&e) => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            Selection::Commits { repository, to, .. } => committed_at(repository, to).map(Some),
+        }
+    }
+
+    /// Issues and pull requests still open within the selection; only milestones have any.
+    pub async fn open_items(&self, repo: &str) -> Result<Vec<Issue>> {
+        match self {
+            Selection::Milestone(version) => {
+                search(&milestone_query(version, repo, "is:open")).await
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Numbers of the pull requests merged between two refs, read from the merge or squash commit
+/// messages returned by the compare API. Empty if `repo` does not have both refs.
+async fn tag_range_pulls(repo: &str, from: &str, to: &str) -> Result<BTreeSet<u64>> {
+    let (owner, name) = owner_and_name(repo);
+    let mut messages = Vec::new();
+    let mut page = 1u32;
+
+    loop {
+        let comparison = match client()
+            .commits(owner, name)
+            .compare(from, to)
+            .per_page(100)
+            .page(page)
+            .send()
+            .await
+        {
+            Ok(comparison) => comparison,
+            Err(e) if is_not_found(&e) => {
+                warn!("{} does not have both {} and {}", repo, from, to);
+                return Ok(BTreeSet::new());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let count = comparison.commits.len();
+        messages.extend(comparison.commits.into_iter().map(|c| c.commit.message));
+        if count < 100 {
+            return Ok(pull_numbers(&messages).await);
+        }
+        page += 1;
+    }
+}
+
+/// The pull requests the merge or squash commits among these messages came from.
+async fn pull_numbers(messages: &[String]) -> BTreeSet<u64> {
+    let mut numbers = BTreeSet::new();
+    for message in messages {
+        if let Some(number) = merged_pull_reference(message).await {
+            numbers.insert(number);
+        }
+    }
+    numbers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_pull_numbers_from_merge_and_squash_commits() {
+        let messages = [
+            "Merge pull request #12 from owner/branch\n\nAdd bids",
+            "Add lots (#34)",
+            "Add lots again (#34)\n\n* Fix tests",
+            "Fix bids (#56)  ",
+        ]
+        .map(String::from);
+        assert_eq!(pull_numbers(&messages).await, BTreeSet::from([12, 34, 56]));
+    }
+
+    #[tokio::test]
+    async fn ignores_commits_not_merged_from_pull_requests() {
+        let messages = [
+            "Fix #12 in the bid table",
+            "Update README\n\nSee (#34)",
+            "Revert (#56) change in bids",
+            "Merge branch 'main' into feature",
+        ]
+        .map(String::from);
+        assert!(pull_numbers(&messages).await.is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{config::config, run, selection::Selection};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{error, info, warn};
+
+/// How many finished runs the status endpoint keeps.
+const RECENT_RUNS: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RunState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// One generation of the release notes, queued by a webhook event.
+#[derive(Clone, Debug, Serialize)]
+struct Run {
+    id: u64,
+    milestone: String,
+    /// The event which queued the run, such as `milestone.closed`.
+    event: String,
+    repository: String,
+    state: RunState,
+    queued_at: DateTime<Utc>,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct Runs {
+    next_id: u64,
+    /// Newest first.
+    runs: VecDeque<Run>,
+}
+
+impl Runs {
+    fn update(&mut self, id: u64, update: impl FnOnce(&mut Run)) {
+        if let Some(run) = self.runs.iter_mut().find(|run| run.id == id) {
+            update(run);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    secret: Arc<String>,
+    runs: Arc<Mutex<Runs>>,
+    queue: UnboundedSender<u64>,
+}
+
+// Only the parts of GitHub's webhook payloads which pick the milestone are read.
+#[derive(Deserialize)]
+struct Event {
+    action: String,
+    repository: Option<Repository>,
+    milestone: Option<Milestone>,
+    release: Option<Release>,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+struct Milestone {
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+/// Listens for GitHub `milestone` and `release` webhooks, generating (and with `publish`,
+/// announcing) the release notes for each closed milestone or published release, one at a time.
+pub async fn serve(
+    listen: SocketAddr,
+    secret: String,
+    editions: Vec<String>,
+    publish: bool,
+) -> Result<i32> {
+    let (queue, jobs) = mpsc::unbounded_channel();
+    let state = AppState {
+        secret: Arc::new(secret),
+        runs: Arc::new(Mutex::new(Runs::default())),
+        queue,
+    };
+
+    let app = Router::new()
+        .route("/webhook", post(webhook))
+        .route("/status", get(status))
+        .with_state(state.clone());
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    info!("Listening for GitHub webhooks on {}", listen);
+
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+    // Runs are worked through here rather than on a spawned task, so that they need not be Send.
+    tokio::select! {
+        result = server => result??,
+        _ = work(&state, jobs, &editions, publish) => {}
+    }
+
+    Ok(0)
+}
+
+async fn work(
+    state: &AppState,
+    mut jobs: UnboundedReceiver<u64>,
+    editions: &[String],
+    publish: bool,
+) {
+    while let Some(id) = jobs.recv().await {
+        let milestone = {
+            let mut runs = state.runs.lock().expect("Run list poisoned");
+            let mut milestone = None;
+            runs.update(id, |run| {
+                run.state = RunState::Running;
+                run.started_at = Some(Utc::now());
+                milestone = Some(run.milestone.clone());
+            });
+            milestone
+        };
+        let Some(milestone) = milestone else {
+            continue;
+        };
+
+        info!("Run {}: generating release notes for {}", id, milestone);
+        let result = run(
+            vec![Selection::Milestone(milestone.clone())],
+            editions,
+            None,
+            false,
+            false,
+            publish,
+        )
+        .await;
+
+        let error = match &result {
+            Ok(_) => {
+                info!("Run {}: finished {}", id, milestone);
+                None
+            }
+            Err(e) => {
+                error!("Run {}: failed for {}: {:?}", id, milestone, e);
+                // Some errors carry a backtrace, which is left to the log.
+                Some(e.to_string().lines().next().unwrap_or_default().to_string())
+            }
+        };
+        state
+            .runs
+            .lock()
+            .expect("Run list poisoned")
+            .update(id, |run| {
+                run.state = match error {
+                    None => RunState::Succeeded,
+                    Some(_) => RunState::Failed,
+                };
+                run.finished_at = Some(Utc::now());
+                run.error = error;
+            });
+    }
+}
+
+async fn webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, String) {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+
+    if !verify(&state.secret, header("X-Hub-Signature-256"), &body) {
+        warn!("Rejected a webhook with a missing or invalid signature");
+        return (StatusCode::UNAUTHORIZED, "Invalid signature".to_string());
+    }
+
+    let kind = header("X-GitHub-Event");
+    if kind == "ping" {
+        return (StatusCode::OK, "pong".to_string());
+    }
+
+    let event: Event = match serde_json::from_slice(&body) {
+        Ok(event) => event,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid payload: {}", e)),
+    };
+
+    let milestone = match (kind, event.action.as_str()) {
+        ("milestone", "closed") => event.milestone.map(|m| m.title),
+        // Releases are expected to be tagged after their milestone, with or without a `v`.
+        ("release", "published") => event
+            .release
+            .map(|r| r.tag_name.trim_start_matches('v').to_string()),
+        _ => None,
+    };
+    let repository = event.repository.map(|r| r.full_name).unwrap_or_default();
+    let Some(milestone) = milestone else {
+        return (
+            StatusCode::ACCEPTED,
+            format!("Ignored {}.{}", kind, event.action),
+        );
+    };
+    if !config()
+        .repositories
+        .iter()
+        .any(|repo| repo.eq_ignore_ascii_case(&repository))
+    {
+        return (
+            StatusCode::ACCEPTED,
+            format!("Ignored event from unconfigured repository {}", repository),
+        );
+    }
+
+    let mut runs = state.runs.lock().expect("Run list poisoned");
+    // Closing the milestone and publishing its release both ask for the same run.
+    if let Some(pending) = runs.runs.iter().find(|run| {
+        run.milestone == milestone && matches!(run.state, RunState::Queued | RunState::Running)
+    }) {
+        return (
+            StatusCode::ACCEPTED,
+            format!("{} is already queued as run {}", milestone, pending.id),
+        );
+    }
+
+    runs.next_id += 1;
+    let id = runs.next_id;
+    runs.runs.push_front(Run {
+        id,
+        milestone: milestone.clone(),
+        event: format!("{}.{}", kind, event.action),
+        repository,
+        state: RunState::Queued,
+        queued_at: Utc::now(),
+        started_at: None,
+        finished_at: None,
+        error: None,
+    });
+    while runs.runs.len() > RECENT_RUNS
+        && runs
+            .runs
+            .back()
+            .is_some_and(|run| matches!(run.state, RunState::Succeeded | RunState::Failed))
+    {
+        runs.runs.pop_back();
+    }
+
+    if state.queue.send(id).is_err() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The run queue has stopped".to_string(),
+        );
+    }
+    info!("Run {}: queued {} for {}", id, milestone, kind);
+    (StatusCode::ACCEPTED, format!("Queued run {}", id))
+}
+
+/// The recent runs, newest first.
+async fn status(State(state): State<AppState>) -> Json<Vec<Run>> {
+    let runs = state.runs.lock().expect("Run list poisoned");
+    Json(runs.runs.iter().cloned().collect())
+}
+
+/// Whether `signature` is the `sha256=<hex>` HMAC of the body with the webhook's secret.
+fn verify(secret: &str, signature: &str, body: &[u8]) -> bool {
+    let Some(signature) = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex| hex::decode(hex).ok())
+    else {
+        return false;
+    };
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example from GitHub's guide to validating webhook deliveries.
+    const SECRET: &str = "It's a Secret to Everybody";
+    const BODY: &[u8] = b"Hello, World!";
+    const SIGNATURE: &str =
+        "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    #[test]
+    fn accepts_the_signature_of_the_body() {
+        assert!(verify(SECRET, SIGNATURE, BODY));
+    }
+
+    #[test]
+    fn rejects_other_bodies_and_secrets() {
+        assert!(!verify(SECRET, SIGNATURE, b"Hello, World?"));
+        assert!(!verify("another secret", SIGNATURE, BODY));
+    }
+
+    #[test]
+    fn rejects_malformed_signatures() {
+        assert!(!verify(
+            SECRET,
+            &SIGNATURE.replace("sha256=", "sha1="),
+            BODY
+        ));
+        assert!(!verify(SECRET, "sha256=not-hex", BODY));
+        assert!(!verify(SECRET, &SIGNATURE[..SIGNATURE.len() - 2], BODY));
+        assert!(!verify(SECRET, "", BODY));
+    }
+}
@@ -0,0 +1,495 @@
+use crate::{
+    config::config,
+    edition::Edition,
+    feed::{self, xml_escape},
+    history::{self, ReleaseSummary},
+    unescape, IssueData, Ticket,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use color_eyre::{eyre::eyre, Result};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{self, DirBuilder},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+/// Where the reports are written.
+const REPORTS: &str = "releases";
+
+/// A release shown on the site, found in the history database, the reports directory or both.
+struct Release {
+    key: String,
+    /// The release's page, relative to the root of the site.
+    page: String,
+    name: String,
+    released_at: NaiveDate,
+    /// The release's figures and tickets, if it is in the history database.
+    recorded: Option<(ReleaseSummary, IssueData)>,
+    /// The Markdown report, if it is in the reports directory.
+    report: Option<PathBuf>,
+}
+
+impl Release {
+    fn issues(&self) -> Option<&IssueData> {
+        self.recorded.as_ref().map(|(_, issues)| issues)
+    }
+}
+
+/// A ticket listed on a module or client page, with the release it shipped in.
+type Listing<'a> = (&'a Release, &'static str, &'a Ticket);
+
+/// Builds a static HTML site of every recorded or reported release in `output`: an index,
+/// a page per release, per module and per client, and an Atom feed.
+pub fn build(output: &Path, editions: &[String], base_url: Option<&str>) -> Result<i32> {
+    let edition = match editions {
+        [] => None,
+        [_] => Edition::selected(editions)?.pop(),
+        _ => return Err(eyre!("The site is built from one edition at a time")),
+    };
+    let releases = releases(edition.as_ref())?;
+    if releases.is_empty() {
+        warn!("No releases are recorded or reported yet, so the site will be empty");
+    }
+
+    for directory in ["releases", "modules", "clients"] {
+        DirBuilder::new()
+            .recursive(true)
+            .create(output.join(directory))?;
+    }
+
+    write_page(
+        output,
+        "index.html",
+        "Market Dojo releases",
+        &index(&releases),
+    )?;
+    for release in &releases {
+        let content = match (&release.report, release.issues()) {
+            (Some(report), _) => markdown(report)?,
+            (None, Some(issues)) => release_page(release, issues),
+            (None, None) => unreachable!("Releases come from the history or the reports"),
+        };
+        write_page(
+            output,
+            &release.page,
+            &format!("Market Dojo {}", release.name),
+            &content,
+        )?;
+    }
+
+    let mut modules: BTreeMap<String, Vec<Listing<'_>>> = BTreeMap::new();
+    let mut clients: BTreeMap<String, Vec<Listing<'_>>> = BTreeMap::new();
+    for release in &releases {
+        let Some(issues) = release.issues() else {
+            continue;
+        };
+        for (kind, tickets) in [
+            ("Client request", &issues.client_requests),
+            ("Feature", &issues.features),
+            ("Bug fix", &issues.bugfixes),
+        ] {
+            for ticket in tickets {
+                for module in &ticket.modules {
+                    modules
+                        .entry(module.clone())
+                        .or_default()
+                        .push((release, kind, ticket));
+                }
+            }
+        }
+        for ticket in &issues.client_requests {
+            for client in unescape(&ticket.detail)
+                .split(", ")
+                .map(str::trim)
+                .filter(|client| !client.is_empty())
+            {
+                clients.entry(client.to_string()).or_default().push((
+                    release,
+                    "Client request",
+                    ticket,
+                ));
+            }
+        }
+    }
+    write_listings(output, "modules", "Modules", "Module", &modules)?;
+    write_listings(output, "clients", "Clients", "Client", &clients)?;
+
+    let link = |page: &str| base_url.map(|base| format!("{}/{}", base.trim_end_matches('/'), page));
+    let entries: Vec<feed::Entry> = releases
+        .iter()
+        .map(|release| {
+            let link = link(&release.page).or_else(|| Some(release.page.clone()));
+            match &release.recorded {
+                Some((summary, issues)) => feed::Entry::new(summary, issues, link),
+                None => feed::Entry {
+                    id: feed::entry_id(&release.key),
+                    title: format!("Market Dojo {}", release.name),
+                    published: feed::midnight(release.released_at),
+                    updated: feed::midnight(release.released_at),
+                    link,
+                    content: format!("<p>Release notes for {}.</p>", xml_escape(&release.name)),
+                },
+            }
+        })
+        .collect();
+    fs::write(
+        output.join("feed.xml"),
+        feed::render(feed::TITLE, link("index.html").as_deref(), &entries),
+    )?;
+
+    info!(
+        "Built the site for {} releases in {}",
+        releases.len(),
+        output.display()
+    );
+    Ok(0)
+}
+
+/// The releases in the history database and the reports directory, newest first. With an
+/// edition, its reports are used and it is applied to the recorded tickets.
+fn releases(edition: Option<&Edition>) -> Result<Vec<Release>> {
+    let mut releases = Vec::new();
+
+    if config().history.enabled {
+        for (summary, issues) in history::recorded()?.into_iter().rev() {
+            let issues = match edition {
+                Some(edition) => edition.apply(&issues),
+                None => issues,
+            };
+            releases.push(Release {
+                key: summary.key.clone(),
+                page: String::new(),
+                name: summary.name.clone(),
+                released_at: summary.released_at,
+                recorded: Some((summary, issues)),
+                report: None,
+            });
+        }
+    }
+
+    let suffix = edition.map(Edition::file_suffix).unwrap_or_default();
+    let directory = Path::new(REPORTS);
+    if directory.is_dir() {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let Some(key) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("release-"))
+                .and_then(|name| name.strip_suffix(".md"))
+                .and_then(|stem| report_key(stem, &suffix))
+            else {
+                continue;
+            };
+
+            match releases.iter_mut().find(|release| release.key == key) {
+                Some(release) => release.report = Some(path),
+                None => {
+                    let modified: DateTime<Utc> = fs::metadata(&path)?.modified()?.into();
+                    releases.push(Release {
+                        name: report_name(&path).unwrap_or_else(|| key.clone()),
+                        key,
+                        page: String::new(),
+                        released_at: modified.date_naive(),
+                        recorded: None,
+                        report: Some(path),
+                    });
+                }
+            }
+        }
+    }
+
+    releases.sort_by_key(|release| std::cmp::Reverse(release.released_at));
+    // Numbered oldest first, so that a page keeps its name as newer releases are added.
+    let pages = slugs(releases.iter().rev().map(|release| release.key.as_str()));
+    for (release, page) in releases.iter_mut().rev().zip(pages) {
+        release.page = format!("releases/{}.html", page);
+    }
+    Ok(releases)
+}
+
+/// The release key of a report file stem, if the report is of the edition with this suffix.
+fn report_key(stem: &str, suffix: &str) -> Option<String> {
+    if !suffix.is_empty() {
+        return stem.strip_suffix(suffix).map(String::from);
+    }
+
+    let other_edition = config()
+        .editions
+        .keys()
+        .any(|name| stem.ends_with(&format!("-{}", name)));
+    (!other_edition).then(|| stem.to_string())
+}
+
+/// The release name from the `# Market Dojo <name>, released <date>` heading of a report.
+fn report_name(path: &Path) -> Option<String> {
+    let report = fs::read_to_string(path).ok()?;
+    let heading = report.lines().next()?.strip_prefix("# Market Dojo ")?;
+    let (name, _) = heading.rsplit_once(", released ")?;
+    Some(name.to_string())
+}
+
+fn index(releases: &[Release]) -> String {
+    let rows = releases
+        .iter()
+        .map(|release| {
+            let counts = |count: fn(&IssueData) -> usize| {
+                release
+                    .issues()
+                    .map(|issues| count(issues).to_string())
+                    .unwrap_or_default()
+            };
+            vec![
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    release.page,
+                    xml_escape(&release.name)
+                ),
+                release.released_at.to_string(),
+                counts(|issues| issues.client_requests.len()),
+                counts(|issues| issues.features.len()),
+                counts(|issues| issues.bugfixes.len()),
+            ]
+        })
+        .collect();
+
+    format!(
+        "<h1>Market Dojo releases</h1>\n{}",
+        table(
+            &[
+                "Release",
+                "Released",
+                "Client requests",
+                "Features",
+                "Bug fixes"
+            ],
+            rows
+        )
+    )
+}
+
+/// A page for a recorded release without a Markdown report, listing its tickets.
+fn release_page(release: &Release, issues: &IssueData) -> String {
+    let mut content = format!(
+        "<h1>Market Dojo {}, released {}</h1>\n",
+        xml_escape(&release.name),
+        release.released_at
+    );
+
+    let breaking = issues.breaking_changes();
+    if !breaking.is_empty() {
+        let rows = breaking
+            .iter()
+            .map(|ticket| {
+                vec![
+                    ticket.id(),
+                    xml_escape(&ticket.plain_description()),
+                    ticket
+                        .migration_notes
+                        .as_deref()
+                        .map(|notes| xml_escape(&unescape(notes)).replace('\n', "<br>"))
+                        .unwrap_or_else(|| "None given.".to_string()),
+                ]
+            })
+            .collect();
+        content.push_str(&format!(
+            "<h2>Breaking Changes</h2>\n{}",
+            table(&["Ticket", "Description", "Migration notes"], rows)
+        ));
+    }
+
+    for (heading, tickets, clients) in [
+        ("Client Requests", &issues.client_requests, true),
+        ("Features and Enhancements", &issues.features, false),
+        ("Bug Fixes", &issues.bugfixes, false),
+    ] {
+        if tickets.is_empty() {
+            continue;
+        }
+        let rows = tickets
+            .iter()
+            .map(|ticket| {
+                let mut cells = vec![ticket.id(), xml_escape(&ticket.plain_description())];
+                if clients {
+                    cells.push(xml_escape(&unescape(&ticket.detail)));
+                }
+                cells
+            })
+            .collect();
+        let headings: &[&str] = if clients {
+            &["Ticket", "Description", "Clients"]
+        } else {
+            &["Ticket", "Description"]
+        };
+        content.push_str(&format!("<h2>{}</h2>\n{}", heading, table(headings, rows)));
+    }
+
+    content
+}
+
+/// An index of the modules or clients, and a page for each listing its tickets by release.
+fn write_listings(
+    output: &Path,
+    directory: &str,
+    title: &str,
+    singular: &str,
+    listings: &BTreeMap<String, Vec<Listing<'_>>>,
+) -> Result<()> {
+    let pages = slugs(listings.keys().map(String::as_str));
+    let rows = listings
+        .iter()
+        .zip(&pages)
+        .map(|((name, tickets), page)| {
+            vec![
+                format!("<a href=\"{}.html\">{}</a>", page, xml_escape(name)),
+                tickets.len().to_string(),
+            ]
+        })
+        .collect();
+    write_page(
+        output,
+        &format!("{}/index.html", directory),
+        title,
+        &format!(
+            "<h1>{}</h1>\n{}",
+            title,
+            table(&[singular, "Tickets"], rows)
+        ),
+    )?;
+
+    for ((name, tickets), page) in listings.iter().zip(&pages) {
+        let rows = tickets
+            .iter()
+            .map(|(release, kind, ticket)| {
+                vec![
+                    format!(
+                        "<a href=\"../{}\">{}</a>",
+                        release.page,
+                        xml_escape(&release.name)
+                    ),
+                    ticket.id(),
+                    kind.to_string(),
+                    xml_escape(&ticket.plain_description()),
+                ]
+            })
+            .collect();
+        write_page(
+            output,
+            &format!("{}/{}.html", directory, page),
+            &format!("{}: {}", singular, name),
+            &format!(
+                "<h1>{}: {}</h1>\n{}",
+                singular,
+                xml_escape(name),
+                table(&["Release", "Ticket", "Type", "Description"], rows)
+            ),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// An HTML table; the cells are HTML already.
+fn table(headings: &[&str], rows: Vec<Vec<String>>) -> String {
+    let header: String = headings
+        .iter()
+        .map(|heading| format!("<th>{}</th>", heading))
+        .collect();
+    let body: String = rows
+        .into_iter()
+        .map(|cells| {
+            let cells: String = cells
+                .iter()
+                .map(|cell| format!("<td>{}</td>", cell))
+                .collect();
+            format!("<tr>{}</tr>\n", cells)
+        })
+        .collect();
+
+    format!(
+        "<table>\n<thead><tr>{}</tr></thead>\n<tbody>\n{}</tbody>\n</table>\n",
+        header, body
+    )
+}
+
+/// A Markdown report as HTML. Raw HTML in the report is shown as text, except for the `<br>`
+/// line breaks the reports put in table cells.
+fn markdown(path: &Path) -> Result<String> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| eyre!("Unable to read report {}: {}", path.display(), e))?;
+    let events = Parser::new_ext(&source, Options::ENABLE_TABLES).map(|event| match event {
+        Event::InlineHtml(tag) if is_line_break(&tag) => Event::HardBreak,
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+
+    let mut content = String::new();
+    html::push_html(&mut content, events);
+    Ok(content)
+}
+
+fn is_line_break(tag: &CowStr<'_>) -> bool {
+    matches!(
+        tag.to_ascii_lowercase().as_str(),
+        "<br>" | "<br/>" | "<br />"
+    )
+}
+
+/// Writes a page at `path` relative to the root of the site.
+fn write_page(output: &Path, path: &str, title: &str, content: &str) -> Result<()> {
+    fs::write(
+        output.join(path),
+        format!(
+            include_str!("../resources/site_page.html.tmpl"),
+            title = xml_escape(title),
+            root = "../".repeat(path.matches('/').count()),
+            content = content,
+        ),
+    )?;
+    Ok(())
+}
+
+/// A file name for each of `names`, in order, numbered where two would otherwise share one.
+/// `index` is kept for the listing pages.
+fn slugs<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut taken = HashSet::from(["index".to_string()]);
+    let mut slugs = Vec::new();
+
+    for name in names {
+        let base = slug(name);
+        let mut candidate = base.clone();
+        let mut number = 2;
+        while !taken.insert(candidate.clone()) {
+            candidate = format!("{}-{}", base, number);
+            number += 1;
+        }
+        slugs.push(candidate);
+    }
+
+    slugs
+}
+
+/// A file name for a release, module or client.
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || c == '.' || c == '_' => c.to_ascii_lowercase(),
+            _ => '-',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_colliding_slugs() {
+        assert_eq!(
+            slugs(["Foo Bar", "foo-bar", "Foo/Bar", "Index", "Other"]),
+            ["foo-bar", "foo-bar-2", "foo-bar-3", "index-2", "other"]
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{config::config, edition::Edition, unescape, IssueData, Ticket};
+use color_eyre::{eyre::eyre, Result};
+use serde_json::{json, Value};
+use std::env;
+use tracing::info;
+
+/// Slack rejects messages with more blocks than this.
+const MAX_BLOCKS: usize = 50;
+/// Slack rejects section blocks with longer text than this.
+const MAX_SECTION_TEXT: usize = 3000;
+/// Slack rejects header blocks with longer text than this.
+const MAX_HEADER_TEXT: usize = 150;
+/// Payloads much larger than this are truncated by Slack, so messages are kept below it.
+const MAX_MESSAGE_BYTES: usize = 30_000;
+
+/// Posts the release announcement to the configured Slack webhook, split across as many
+/// messages as Slack's size limits need.
+pub async fn announce(issues: &IssueData, name: &str, stem: &str) -> Result<()> {
+    let settings = &config().slack;
+    let Some(webhook) = webhook() else {
+        return Ok(());
+    };
+
+    let issues = match &settings.edition {
+        Some(edition) => Edition::selected(std::slice::from_ref(edition))?[0].apply(issues),
+        None => issues.clone(),
+    };
+
+    let messages = messages(name, blocks(&issues, name, stem));
+    let client = reqwest::Client::new();
+    for message in &messages {
+        let response = client.post(&webhook).json(message).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(eyre!(
+                "Slack rejected the announcement with {}: {}",
+                status,
+                response.text().await.unwrap_or_default()
+            ));
+        }
+    }
+
+    info!(
+        "Announced {} in Slack in {} message(s)",
+        name,
+        messages.len()
+    );
+    Ok(())
+}
+
+/// The incoming webhook from `SLACK_WEBHOOK_URL` or the config, if Slack is set up.
+pub fn webhook() -> Option<String> {
+    env::var("SLACK_WEBHOOK_URL")
+        .ok()
+        .or_else(|| config().slack.webhook_url.clone())
+}
+
+/// The announcement as Block Kit blocks: a headline with counts, any breaking changes, links to
+/// the full release notes, then the client requests and features.
+fn blocks(issues: &IssueData, name: &str, stem: &str) -> Vec<Value> {
+    let settings = &config().slack;
+    let link = |template: &Option<String>| {
+        template
+            .as_ref()
+            .map(|template| template.replace("{release}", stem))
+    };
+    let release_url = link(&settings.release_url);
+    let pdf_url = link(&settings.pdf_url);
+
+    let mut blocks = vec![
+        json!({
+            "type": "header",
+            "text": {"type": "plain_text", "text": truncate(&format!("Release {}", name), MAX_HEADER_TEXT)},
+        }),
+        section(&format!(
+            "*{}* client requests  •  *{}* features  •  *{}* bug fixes",
+            issues.client_requests.len(),
+            issues.features.len(),
+            issues.bugfixes.len()
+        )),
+    ];
+
+    let breaking: Vec<Ticket> = issues.breaking_changes().into_iter().cloned().collect();
+    if !breaking.is_empty() {
+        blocks.extend(list(
+            ":warning: Breaking changes",
+            &breaking,
+            |ticket| {
+                ticket
+                    .plain_migration_notes()
+                    .map(|notes| format!("Migration: {}", notes))
+            },
+            release_url.as_deref(),
+            settings.collapse_after,
+        ));
+    }
+
+    let buttons: Vec<Value> = [("GitHub Release", &release_url), ("PDF", &pdf_url)]
+        .into_iter()
+        .filter_map(|(text, url)| {
+            url.as_ref().map(|url| {
+                json!({
+                    "type": "button",
+                    "text": {"type": "plain_text", "text": text},
+                    "url": url,
+                })
+            })
+        })
+        .collect();
+    if !buttons.is_empty() {
+        blocks.push(json!({"type": "actions", "elements": buttons}));
+    }
+
+    let clients = |ticket: &Ticket| Some(unescape(&ticket.detail));
+    for (heading, tickets, detail) in [
+        (
+            "Client requests",
+            &issues.client_requests,
+            clients as fn(&Ticket) -> Option<String>,
+        ),
+        ("Features", &issues.features, |_| None),
+    ] {
+        if tickets.is_empty() {
+            continue;
+        }
+        blocks.push(json!({"type": "divider"}));
+        blocks.extend(list(
+            heading,
+            tickets,
+            detail,
+            release_url.as_deref(),
+            settings.collapse_after,
+        ));
+    }
+
+    blocks
+}
+
+/// A bulleted list of tickets, each followed by its `detail` if it has one, in as many section
+/// blocks as it needs, collapsed to `collapse_after` tickets (unless 0) with a note of how many
+/// more the full release notes hold.
+fn list(
+    heading: &str,
+    tickets: &[Ticket],
+    detail: fn(&Ticket) -> Option<String>,
+    more_url: Option<&str>,
+    collapse_after: usize,
+) -> Vec<Value> {
+    let collapse_after = match collapse_after {
+        0 => tickets.len(),
+        n => n,
+    };
+
+    let mut lines = vec![format!("*{}*", heading)];
+    lines.extend(tickets.iter().take(collapse_after).map(|ticket| {
+        let mut line = format!(
+            "• {} {}",
+            ticket.reference(),
+            mrkdwn(&ticket.plain_description())
+        );
+        if let Some(detail) = detail(ticket) {
+            line.push_str(&format!(" — _{}_", mrkdwn(&detail)));
+        }
+        truncate(&line, MAX_SECTION_TEXT)
+    }));
+
+    let mut blocks = Vec::new();
+    let mut text = String::new();
+    for line in lines {
+        if !text.is_empty() && text.len() + line.len() + 1 > MAX_SECTION_TEXT {
+            blocks.push(section(&text));
+            text.clear();
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&line);
+    }
+    blocks.push(section(&text));
+
+    let hidden = tickets.len().saturating_sub(collapse_after);
+    if hidden > 0 {
+        let notes = match more_url {
+            Some(url) => format!("<{}|full release notes>", url),
+            None => "full release notes".to_string(),
+        };
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{"type": "mrkdwn", "text": format!("…and {} more in the {}", hidden, notes)}],
+        }));
+    }
+
+    blocks
+}
+
+/// Splits the blocks into messages within Slack's limits, each with the fallback text shown in
+/// notifications.
+fn messages(name: &str, blocks: Vec<Value>) -> Vec<Value> {
+    let mut messages = Vec::new();
+    let mut current: Vec<Value> = Vec::new();
+    let mut size = 0;
+
+    for block in blocks {
+        let block_size = block.to_string().len();
+        if !current.is_empty()
+            && (current.len() == MAX_BLOCKS || size + block_size > MAX_MESSAGE_BYTES)
+        {
+            messages.push(std::mem::take(&mut current));
+            size = 0;
+        }
+        size += block_size;
+        current.push(block);
+    }
+    messages.push(current);
+
+    messages
+        .into_iter()
+        .enumerate()
+        .map(|(index, blocks)| {
+            let text = match index {
+                0 => format!("Release {}", name),
+                _ => format!("Release {} (continued)", name),
+            };
+            json!({"text": text, "blocks": blocks})
+        })
+        .collect()
+}
+
+fn section(text: &str) -> Value {
+    json!({"type": "section", "text": {"type": "mrkdwn", "text": text}})
+}
+
+/// Escapes the characters Slack's mrkdwn uses for links and mentions.
+fn mrkdwn(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tickets(count: u64, title: &str) -> Vec<Ticket> {
+        (1..=count)
+            .map(|number| Ticket::fixture(number, title))
+            .collect()
+    }
+
+    fn texts(blocks: &[Value]) -> Vec<&str> {
+        blocks
+            .iter()
+            .filter_map(|block| block["text"]["text"].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn splits_messages_at_the_block_limit() {
+        let blocks = (0..120).map(|i| section(&i.to_string())).collect();
+        let messages = messages("1.2", blocks);
+
+        let sizes: Vec<usize> = messages
+            .iter()
+            .map(|message| message["blocks"].as_array().unwrap().len())
+            .collect();
+        assert_eq!(sizes, [50, 50, 20]);
+        assert_eq!(messages[0]["text"], "Release 1.2");
+        assert_eq!(messages[2]["text"], "Release 1.2 (continued)");
+    }
+
+    #[test]
+    fn splits_messages_below_the_size_limit() {
+        let blocks = (0..5).map(|_| section(&"x".repeat(10_000))).collect();
+        let messages = messages("1.2", blocks);
+
+        let sizes: Vec<usize> = messages
+            .iter()
+            .map(|message| message["blocks"].as_array().unwrap().len())
+            .collect();
+        assert_eq!(sizes, [2, 2, 1]);
+    }
+
+    #[test]
+    fn truncates_at_a_character_boundary() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("ééééééé", 5), "éééé…");
+        assert_eq!(truncate("ééééééé", 5).chars().count(), 5);
+    }
+
+    #[test]
+    fn splits_long_lists_across_sections_within_the_text_limit() {
+        let blocks = list(
+            "Features",
+            &tickets(3, &"a".repeat(2_000)),
+            |_| None,
+            None,
+            0,
+        );
+
+        assert_eq!(blocks.len(), 3);
+        assert!(texts(&blocks)
+            .iter()
+            .all(|text| text.chars().count() <= MAX_SECTION_TEXT));
+        assert!(texts(&blocks)[0].starts_with("*Features*\n• #1 "));
+    }
+
+    #[test]
+    fn truncates_lines_longer_than_a_section() {
+        let blocks = list(
+            "Features",
+            &tickets(1, &"é".repeat(4_000)),
+            |_| None,
+            None,
+            0,
+        );
+
+        let texts = texts(&blocks);
+        assert_eq!(texts.len(), 2);
+        assert_eq!(texts[1].chars().count(), MAX_SECTION_TEXT);
+        assert!(texts[1].ends_with('…'));
+    }
+
+    #[test]
+    fn collapses_lists_after_the_configured_number_of_tickets() {
+        let blocks = list(
+            "Features",
+            &tickets(5, "Add bids"),
+            |_| None,
+            Some("https://example.com/1.2"),
+            2,
+        );
+
+        assert_eq!(texts(&blocks), ["*Features*\n• #1 Add bids\n• #2 Add bids"]);
+        assert_eq!(
+            blocks[1]["elements"][0]["text"],
+            "…and 3 more in the <https://example.com/1.2|full release notes>"
+        );
+    }
+
+    #[test]
+    fn lists_every_ticket_when_not_collapsing() {
+        let blocks = list("Features", &tickets(5, "Add bids"), |_| None, None, 0);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(texts(&blocks)[0].lines().count(), 6);
+    }
+}
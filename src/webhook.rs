@@ -0,0 +1,436 @@
+use crate::{
+    config::{config, WebhookConfig},
+    edition::Edition,
+    unescape, IssueData, Ticket,
+};
+use color_eyre::{eyre::eyre, Result};
+use minijinja::{AutoEscape, Environment};
+use serde::Serialize;
+use std::{env, fs, time::Duration};
+use tracing::{info, warn};
+
+/// How long to wait before the first retry; each later retry waits twice as long.
+#[cfg(not(test))]
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+#[cfg(test)]
+const FIRST_BACKOFF: Duration = Duration::from_millis(10);
+
+/// The release data webhook templates are rendered with.
+#[derive(Serialize)]
+struct Payload {
+    release: Release,
+    counts: Counts,
+    /// The shipped tickets of any section which are breaking changes.
+    breaking_changes: Vec<TicketData>,
+    client_requests: Vec<TicketData>,
+    features: Vec<TicketData>,
+    bugfixes: Vec<TicketData>,
+    /// The non-empty ticket lists, for templates which treat them alike.
+    sections: Vec<SectionData>,
+    release_url: Option<String>,
+    pdf_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Release {
+    name: String,
+    /// The report's file stem, such as the milestone number.
+    key: String,
+}
+
+#[derive(Serialize)]
+struct Counts {
+    breaking_changes: usize,
+    client_requests: usize,
+    features: usize,
+    bugfixes: usize,
+    carried_over: usize,
+}
+
+/// A ticket in plain text, without the escaping the LaTeX report needs.
+#[derive(Clone, Serialize)]
+struct TicketData {
+    number: u64,
+    /// The commit hash, for tickets read from a local repository with `--git`.
+    commit: Option<String>,
+    /// How the ticket is referred to, such as `#123` or an abbreviated commit hash.
+    reference: String,
+    title: String,
+    /// The release notes on one line if there are any, otherwise the title.
+    description: String,
+    /// The requesting clients, for client requests.
+    clients: Option<String>,
+    author: String,
+    modules: Vec<String>,
+    labels: Vec<String>,
+    pulls: Vec<PullData>,
+    breaking: bool,
+    migration_notes: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct PullData {
+    number: u64,
+    url: String,
+    author: String,
+}
+
+#[derive(Serialize)]
+struct SectionData {
+    /// A stable identifier, such as `features`.
+    id: &'static str,
+    heading: &'static str,
+    tickets: Vec<TicketData>,
+}
+
+/// Announces the release to every configured webhook, stopping at the first which fails.
+pub async fn publish(issues: &IssueData, name: &str, stem: &str) -> Result<()> {
+    let client = reqwest::Client::new();
+    for webhook in &config().webhooks {
+        announce(&client, webhook, issues, name, stem).await?;
+    }
+    Ok(())
+}
+
+async fn announce(
+    client: &reqwest::Client,
+    webhook: &WebhookConfig,
+    issues: &IssueData,
+    name: &str,
+    stem: &str,
+) -> Result<()> {
+    let url = match (&webhook.url, &webhook.url_env) {
+        (Some(url), _) => url.clone(),
+        (None, Some(variable)) => env::var(variable).map_err(|_| {
+            eyre!(
+                "Webhook {} needs its URL in the {} environment variable",
+                webhook.name,
+                variable
+            )
+        })?,
+        (None, None) => return Err(eyre!("Webhook {} needs a url or url_env", webhook.name)),
+    };
+
+    let issues = match &webhook.edition {
+        Some(edition) => Edition::selected(std::slice::from_ref(edition))?[0].apply(issues),
+        None => issues.clone(),
+    };
+    let body = render(webhook, &payload(webhook, &issues, name, stem))?;
+
+    let mut backoff = FIRST_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        let mut request = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(body.clone());
+        for (header, value) in &webhook.headers {
+            request = request.header(header, value);
+        }
+
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => break,
+            Ok(response) => {
+                let status = response.status();
+                let error = eyre!(
+                    "Webhook {} rejected the announcement with {}: {}",
+                    webhook.name,
+                    status,
+                    response.text().await.unwrap_or_default()
+                );
+                // Other client errors will fail the same way however often they are retried.
+                if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    return Err(error);
+                }
+                error
+            }
+            Err(e) => eyre!("Unable to reach webhook {}: {}", webhook.name, e),
+        };
+
+        if attempt == webhook.retries {
+            return Err(error);
+        }
+        attempt += 1;
+        warn!("{}; retrying in {:?}", error, backoff);
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+
+    info!("Announced {} to webhook {}", name, webhook.name);
+    Ok(())
+}
+
+/// Renders the webhook's template, or the Teams Adaptive Card, checking that the result is JSON.
+/// Values are inserted as JSON, so `"text": {{ release.name }}` gives a quoted, escaped string.
+fn render(webhook: &WebhookConfig, payload: &Payload) -> Result<String> {
+    let source = match &webhook.template {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| eyre!("Unable to read template {}: {}", path.display(), e))?,
+        None => include_str!("../resources/teams_card.json.tmpl").to_string(),
+    };
+
+    let mut environment = Environment::new();
+    environment.set_auto_escape_callback(|_| AutoEscape::Json);
+    let body = environment.render_str(&source, payload).map_err(|e| {
+        eyre!(
+            "Unable to render the template for webhook {}: {}",
+            webhook.name,
+            e
+        )
+    })?;
+
+    serde_json::from_str::<serde_json::Value>(&body).map_err(|e| {
+        eyre!(
+            "The template for webhook {} did not render valid JSON: {}",
+            webhook.name,
+            e
+        )
+    })?;
+    Ok(body)
+}
+
+fn payload(webhook: &WebhookConfig, issues: &IssueData, name: &str, stem: &str) -> Payload {
+    let link = |template: &Option<String>| {
+        template
+            .as_ref()
+            .map(|template| template.replace("{release}", stem))
+    };
+    let tickets = |tickets: &[Ticket], clients: bool| -> Vec<TicketData> {
+        tickets
+            .iter()
+            .map(|ticket| ticket_data(ticket, clients))
+            .collect()
+    };
+
+    let breaking_changes: Vec<TicketData> = issues
+        .breaking_changes()
+        .into_iter()
+        .map(|ticket| ticket_data(ticket, false))
+        .collect();
+    let client_requests = tickets(&issues.client_requests, true);
+    let features = tickets(&issues.features, false);
+    let bugfixes = tickets(&issues.bugfixes, false);
+
+    let sections = [
+        ("breaking_changes", "Breaking changes", &breaking_changes),
+        ("client_requests", "Client requests", &client_requests),
+        ("features", "Features", &features),
+        ("bugfixes", "Bug fixes", &bugfixes),
+    ]
+    .into_iter()
+    .filter(|(_, _, tickets)| !tickets.is_empty())
+    .map(|(id, heading, tickets)| SectionData {
+        id,
+        heading,
+        tickets: tickets.clone(),
+    })
+    .collect();
+
+    Payload {
+        release: Release {
+            name: name.to_string(),
+            key: stem.to_string(),
+        },
+        counts: Counts {
+            breaking_changes: breaking_changes.len(),
+            client_requests: client_requests.len(),
+            features: features.len(),
+            bugfixes: bugfixes.len(),
+            carried_over: issues.carried_over.len(),
+        },
+        breaking_changes,
+        client_requests,
+        features,
+        bugfixes,
+        sections,
+        release_url: link(&webhook.release_url),
+        pdf_url: link(&webhook.pdf_url),
+    }
+}
+
+fn ticket_data(ticket: &Ticket, clients: bool) -> TicketData {
+    TicketData {
+        number: ticket.number,
+        commit: ticket.commit.clone(),
+        reference: ticket.reference(),
+        title: unescape(&ticket.title),
+        description: ticket.plain_description(),
+        clients: clients.then(|| unescape(&ticket.detail)),
+        author: unescape(&ticket.author),
+        modules: ticket.modules.clone(),
+        labels: ticket.labels.clone(),
+        pulls: ticket
+            .pulls
+            .iter()
+            .map(|pull| PullData {
+                number: pull.number,
+                url: pull.url.clone(),
+                author: pull.author.clone(),
+            })
+            .collect(),
+        breaking: ticket.breaking,
+        migration_notes: ticket.plain_migration_notes(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    fn issues() -> IssueData {
+        IssueData {
+            client_requests: vec![Ticket {
+                detail: "Acme".to_string(),
+                breaking: true,
+                migration_notes: Some("Send a currency".to_string()),
+                ..Ticket::fixture(12, r#"Add "quoted" \& <bold> bids"#)
+            }],
+            features: vec![Ticket {
+                feature: true,
+                ..Ticket::fixture(34, "Add lots")
+            }],
+            ..IssueData::default()
+        }
+    }
+
+    fn webhook(url: Option<String>, retries: u32) -> WebhookConfig {
+        WebhookConfig {
+            name: "teams".to_string(),
+            url,
+            release_url: Some("https://example.com/releases/{release}".to_string()),
+            retries,
+            ..WebhookConfig::default()
+        }
+    }
+
+    /// Answers one request with each status in turn, recording the bodies it was sent.
+    async fn stand_in(statuses: &[u16]) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+
+        let received = bodies.clone();
+        let statuses = statuses.to_vec();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                let body = loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let Some((headers, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = headers
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break body.to_string();
+                    }
+                };
+                received.lock().unwrap().push(body);
+
+                let response = format!(
+                    "HTTP/1.1 {} Stand-in\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+
+        (url, bodies)
+    }
+
+    #[test]
+    fn renders_the_teams_card() {
+        let webhook = webhook(None, 0);
+        let body = render(&webhook, &payload(&webhook, &issues(), "1.2", "1.2")).unwrap();
+        let card: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        let content = &card["attachments"][0]["content"];
+        assert_eq!(content["body"][0]["text"], "Release 1.2");
+        assert_eq!(
+            content["body"][1]["text"],
+            "This release has 1 breaking change(s); read the migration notes before upgrading."
+        );
+        assert_eq!(
+            content["body"][4]["items"][0]["text"],
+            r#"#12 Add "quoted" & <bold> bids — Migration: Send a currency"#
+        );
+        assert_eq!(
+            content["actions"][0]["url"],
+            "https://example.com/releases/1.2"
+        );
+    }
+
+    #[test]
+    fn rejects_templates_which_do_not_render_json() {
+        let path =
+            std::env::temp_dir().join(format!("release_bot_webhook_{}.json", std::process::id()));
+        fs::write(&path, "{\"text\": {{ release.name }}").unwrap();
+        let webhook = WebhookConfig {
+            template: Some(path.clone()),
+            ..webhook(None, 0)
+        };
+
+        let error = render(&webhook, &payload(&webhook, &issues(), "1.2", "1.2")).unwrap_err();
+        fs::remove_file(path).unwrap();
+        assert!(error.to_string().contains("did not render valid JSON"));
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_and_rate_limits() {
+        let (url, bodies) = stand_in(&[503, 429, 204]).await;
+        let webhook = webhook(Some(url), 2);
+
+        announce(&reqwest::Client::new(), &webhook, &issues(), "1.2", "1.2")
+            .await
+            .unwrap();
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 3);
+        assert!(serde_json::from_str::<serde_json::Value>(&bodies[0]).is_ok());
+        assert_eq!(bodies[0], bodies[2]);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_other_client_errors() {
+        let (url, bodies) = stand_in(&[400, 204]).await;
+        let webhook = webhook(Some(url), 2);
+
+        let error = announce(&reqwest::Client::new(), &webhook, &issues(), "1.2", "1.2")
+            .await
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("rejected the announcement with 400"));
+        assert_eq!(bodies.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_configured_retries() {
+        let (url, bodies) = stand_in(&[500, 502, 204]).await;
+        let webhook = webhook(Some(url), 1);
+
+        let error = announce(&reqwest::Client::new(), &webhook, &issues(), "1.2", "1.2")
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("502"));
+        assert_eq!(bodies.lock().unwrap().len(), 2);
+    }
+}